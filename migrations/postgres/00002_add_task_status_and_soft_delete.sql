@@ -0,0 +1,8 @@
+ALTER TABLE tasks
+    ADD COLUMN status       VARCHAR(32) NOT NULL DEFAULT 'open'
+        CHECK (status IN ('open', 'in_progress', 'done', 'cancelled')),
+    ADD COLUMN completed_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN deleted_at   TIMESTAMP WITH TIME ZONE;
+
+-- Create partial index on status for the common "open tasks that aren't deleted" lookups
+CREATE INDEX idx_tasks_status ON tasks(status) WHERE deleted_at IS NULL;
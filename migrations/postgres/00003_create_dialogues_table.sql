@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS dialogues
+(
+    chat_id    BIGINT PRIMARY KEY,
+    state      JSONB NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+-- Create index on updated_at for expiring stale dialogues
+CREATE INDEX idx_dialogues_updated_at ON dialogues(updated_at);
@@ -0,0 +1,17 @@
+-- Append-only change history; rows are never updated or deleted
+CREATE TABLE IF NOT EXISTS task_events
+(
+    event_id    UUID PRIMARY KEY,
+    task_id     UUID NOT NULL REFERENCES tasks(task_id),
+    occurred_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    actor       VARCHAR(255) NOT NULL,
+    source_text TEXT NOT NULL,
+    intent      VARCHAR(50) NOT NULL,
+    params      JSONB NOT NULL,
+    before      JSONB,
+    after       JSONB NOT NULL
+);
+
+-- Create indexes for per-task history and per-actor lookups
+CREATE INDEX idx_task_events_task_id ON task_events(task_id, occurred_at);
+CREATE INDEX idx_task_events_actor ON task_events(actor, occurred_at);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS approved_members
+(
+    user_id      BIGINT PRIMARY KEY,
+    approved_via VARCHAR(20) NOT NULL CHECK (approved_via IN ('passphrase', 'allow_list')),
+    approved_at  TIMESTAMP WITH TIME ZONE NOT NULL
+);
@@ -0,0 +1,18 @@
+-- The chat each assignee gets reminders in; names are stored lowercased to match assignees
+-- case-insensitively
+CREATE TABLE IF NOT EXISTS assignee_chats
+(
+    assignee  VARCHAR(255) PRIMARY KEY,
+    chat_id   BIGINT NOT NULL,
+    linked_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+-- One row per reminder sent. The due date is part of the key so a rescheduled task is reminded again
+CREATE TABLE IF NOT EXISTS reminders_sent
+(
+    task_id        UUID NOT NULL REFERENCES tasks(task_id),
+    offset_minutes BIGINT NOT NULL,
+    due_date       TIMESTAMP WITH TIME ZONE NOT NULL,
+    sent_at        TIMESTAMP WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (task_id, offset_minutes, due_date)
+);
@@ -0,0 +1,15 @@
+-- Timestamps are stored as RFC 3339 text and UUIDs as 16-byte blobs, matching sqlx's SQLite encoding
+CREATE TABLE IF NOT EXISTS tasks
+(
+    task_id     BLOB PRIMARY KEY,
+    description TEXT NOT NULL,
+    create_date TEXT NOT NULL,
+    due_date    TEXT NOT NULL,
+    assignee    TEXT NOT NULL
+);
+
+-- Create index on assignee for faster lookups
+CREATE INDEX idx_tasks_assignee ON tasks(assignee);
+
+-- Create index on due_date for efficient querying of upcoming tasks
+CREATE INDEX idx_tasks_due_date ON tasks(due_date);
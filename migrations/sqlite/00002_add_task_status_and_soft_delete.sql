@@ -0,0 +1,7 @@
+ALTER TABLE tasks ADD COLUMN status TEXT NOT NULL DEFAULT 'open'
+    CHECK (status IN ('open', 'in_progress', 'done', 'cancelled'));
+ALTER TABLE tasks ADD COLUMN completed_at TEXT;
+ALTER TABLE tasks ADD COLUMN deleted_at TEXT;
+
+-- Create partial index on status for the common "open tasks that aren't deleted" lookups
+CREATE INDEX idx_tasks_status ON tasks(status) WHERE deleted_at IS NULL;
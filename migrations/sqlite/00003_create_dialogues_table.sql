@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS dialogues
+(
+    chat_id    INTEGER PRIMARY KEY,
+    state      TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+-- Create index on updated_at for expiring stale dialogues
+CREATE INDEX idx_dialogues_updated_at ON dialogues(updated_at);
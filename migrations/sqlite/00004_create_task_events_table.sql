@@ -0,0 +1,17 @@
+-- Append-only change history; rows are never updated or deleted
+CREATE TABLE IF NOT EXISTS task_events
+(
+    event_id    BLOB PRIMARY KEY,
+    task_id     BLOB NOT NULL REFERENCES tasks(task_id),
+    occurred_at TEXT NOT NULL,
+    actor       TEXT NOT NULL,
+    source_text TEXT NOT NULL,
+    intent      TEXT NOT NULL,
+    params      TEXT NOT NULL,
+    before      TEXT,
+    after       TEXT NOT NULL
+);
+
+-- Create indexes for per-task history and per-actor lookups
+CREATE INDEX idx_task_events_task_id ON task_events(task_id, occurred_at);
+CREATE INDEX idx_task_events_actor ON task_events(actor, occurred_at);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS approved_members
+(
+    user_id      INTEGER PRIMARY KEY,
+    approved_via TEXT NOT NULL CHECK (approved_via IN ('passphrase', 'allow_list')),
+    approved_at  TEXT NOT NULL
+);
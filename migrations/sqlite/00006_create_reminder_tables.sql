@@ -0,0 +1,18 @@
+-- The chat each assignee gets reminders in; names are stored lowercased to match assignees
+-- case-insensitively
+CREATE TABLE IF NOT EXISTS assignee_chats
+(
+    assignee  TEXT PRIMARY KEY,
+    chat_id   INTEGER NOT NULL,
+    linked_at TEXT NOT NULL
+);
+
+-- One row per reminder sent. The due date is part of the key so a rescheduled task is reminded again
+CREATE TABLE IF NOT EXISTS reminders_sent
+(
+    task_id        BLOB NOT NULL REFERENCES tasks(task_id),
+    offset_minutes INTEGER NOT NULL,
+    due_date       TEXT NOT NULL,
+    sent_at        TEXT NOT NULL,
+    PRIMARY KEY (task_id, offset_minutes, due_date)
+);
@@ -0,0 +1,11 @@
+-- Scheduled summaries per chat. send_time is a wall-clock HH:MM in the chat's own timezone, and
+-- last_sent_on the local date of the last digest sent, so a restart doesn't send it twice
+CREATE TABLE IF NOT EXISTS digest_schedules
+(
+    chat_id      INTEGER NOT NULL,
+    kind         TEXT NOT NULL CHECK (kind IN ('daily', 'weekly')),
+    send_time    TEXT NOT NULL,
+    timezone     TEXT NOT NULL,
+    last_sent_on TEXT,
+    PRIMARY KEY (chat_id, kind)
+);
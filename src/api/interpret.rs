@@ -0,0 +1,231 @@
+use super::{ApiErr, MAX_ACTOR_LEN};
+use crate::conversation::{self, Button, Inbound, InteractionSteps, Reply};
+use crate::domain::task::service::TaskDataFlows;
+use crate::llm::interface::LLMClient;
+use crate::transcription::interface::TranscriptionClient;
+use axum::async_trait;
+use axum::extract::{DefaultBodyLimit, FromRequest, Multipart, Request, State};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+/// Whisper rejects files larger than this, so there is no point accepting more.
+const MAX_AUDIO_BYTES: usize = 25 * 1024 * 1024;
+/// Recorded as the requester when the request doesn't name a user.
+const DEFAULT_USER: &str = "api";
+
+/// The conversation engine for clients that can't use Telegram, with follow-up questions carried
+/// across requests by conversation id.
+pub struct Interpreter<T, L, S> {
+    pub engine: conversation::Engine<T, L, S, Uuid>,
+    pub conversations: Conversations,
+}
+
+/// The step each HTTP conversation is waiting on. Conversations idle for longer than the TTL
+/// start over, like Telegram dialogues do.
+pub struct Conversations {
+    ttl: Duration,
+    steps: Mutex<HashMap<Uuid, (DateTime<Utc>, InteractionSteps)>>,
+}
+impl Conversations {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            steps: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Removes and returns the pending step; the handler puts back whatever comes next.
+    fn take(&self, id: Uuid) -> InteractionSteps {
+        let mut steps = self.steps.lock().expect("conversation lock poisoned");
+        let now = Utc::now();
+        steps.retain(|_, (updated_at, _)| now - *updated_at < self.ttl);
+        steps.remove(&id).map(|(_, step)| step).unwrap_or_default()
+    }
+
+    fn put(&self, id: Uuid, step: InteractionSteps) {
+        let mut steps = self.steps.lock().expect("conversation lock poisoned");
+        match step {
+            InteractionSteps::ReceiveInput => steps.remove(&id),
+            step => steps.insert(id, (Utc::now(), step)),
+        };
+    }
+}
+
+pub fn router<T, L, S>(interpreter: Arc<Interpreter<T, L, S>>) -> Router
+where
+    T: TranscriptionClient + Send + Sync + 'static,
+    L: LLMClient<String> + Send + Sync + 'static,
+    S: TaskDataFlows + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/interpret", post(interpret::<T, L, S>))
+        .layer(DefaultBodyLimit::max(MAX_AUDIO_BYTES))
+        .with_state(interpreter)
+}
+
+/// A JSON body, or a `multipart/form-data` form with the same fields plus an `audio` file.
+#[derive(Debug, Default, Deserialize)]
+pub struct InterpretRequest {
+    /// Continues an earlier conversation; omit to start a new one.
+    pub conversation_id: Option<Uuid>,
+    pub text: Option<String>,
+    /// Who is asking, for the task history.
+    pub user: Option<String>,
+    /// Answers a pending confirmation, like pressing Confirm (`true`) or Cancel (`false`).
+    pub confirm: Option<bool>,
+    /// The `nonce` of the confirmation being answered, required with `confirm`. A stale one is
+    /// refused.
+    pub nonce: Option<Uuid>,
+    /// Transcribed and used instead of `text`.
+    #[serde(skip)]
+    pub audio: Option<Vec<u8>>,
+}
+
+#[async_trait]
+impl<St: Send + Sync> FromRequest<St> for InterpretRequest {
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &St) -> Result<Self, Self::Rejection> {
+        let is_multipart = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .is_some_and(|content_type| content_type.starts_with("multipart/form-data"));
+        if !is_multipart {
+            let Json(request) = Json::<Self>::from_request(req, state)
+                .await
+                .map_err(IntoResponse::into_response)?;
+            return Ok(request);
+        }
+        let mut multipart = Multipart::from_request(req, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let mut request = Self::default();
+        while let Some(field) = multipart
+            .next_field()
+            .await
+            .map_err(IntoResponse::into_response)?
+        {
+            let name = field.name().unwrap_or_default().to_string();
+            match name.as_str() {
+                "audio" => {
+                    let audio = field.bytes().await.map_err(IntoResponse::into_response)?;
+                    request.audio = Some(audio.to_vec());
+                }
+                "text" => {
+                    request.text = Some(field.text().await.map_err(IntoResponse::into_response)?)
+                }
+                "user" => {
+                    request.user = Some(field.text().await.map_err(IntoResponse::into_response)?)
+                }
+                "confirm" => {
+                    let confirm = field.text().await.map_err(IntoResponse::into_response)?;
+                    request.confirm = Some(confirm.trim().parse().map_err(|_| {
+                        ApiErr::Invalid {
+                            message: String::from("confirm must be true or false"),
+                            fields: vec!["confirm"],
+                        }
+                        .into_response()
+                    })?);
+                }
+                "nonce" => {
+                    let nonce = field.text().await.map_err(IntoResponse::into_response)?;
+                    request.nonce = Some(nonce.trim().parse().map_err(|_| {
+                        ApiErr::Invalid {
+                            message: String::from("nonce must be a UUID"),
+                            fields: vec!["nonce"],
+                        }
+                        .into_response()
+                    })?);
+                }
+                "conversation_id" => {
+                    let id = field.text().await.map_err(IntoResponse::into_response)?;
+                    request.conversation_id = Some(id.trim().parse().map_err(|_| {
+                        ApiErr::Invalid {
+                            message: String::from("conversation_id must be a UUID"),
+                            fields: vec!["conversation_id"],
+                        }
+                        .into_response()
+                    })?);
+                }
+                _ => {}
+            }
+        }
+        Ok(request)
+    }
+}
+
+#[derive(Serialize)]
+pub struct InterpretResponse {
+    /// Send this back with the answer to a follow-up question.
+    pub conversation_id: Uuid,
+    /// What the uploaded audio was heard as.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transcript: Option<String>,
+    #[serde(flatten)]
+    pub reply: Reply,
+}
+
+async fn interpret<T, L, S>(
+    State(interpreter): State<Arc<Interpreter<T, L, S>>>,
+    request: InterpretRequest,
+) -> Result<Json<InterpretResponse>, ApiErr>
+where
+    T: TranscriptionClient,
+    L: LLMClient<String>,
+    S: TaskDataFlows,
+{
+    let conversation_id = request.conversation_id.unwrap_or_else(Uuid::new_v4);
+    let event = match (request.confirm, request.audio, request.text) {
+        (Some(confirm), _, _) => Inbound::Button {
+            button: if confirm {
+                Button::Confirm
+            } else {
+                Button::Cancel
+            },
+            nonce: request.nonce.ok_or_else(|| ApiErr::Invalid {
+                message: String::from("confirm needs the nonce of the confirmation it answers"),
+                fields: vec!["nonce"],
+            })?,
+        },
+        (None, Some(audio), _) => Inbound::Audio(audio),
+        (None, None, Some(text)) if !text.trim().is_empty() => Inbound::Text(text),
+        _ => {
+            return Err(ApiErr::Invalid {
+                message: String::from("send text, an audio file or a confirm answer"),
+                fields: vec!["text", "audio", "confirm"],
+            })
+        }
+    };
+    let user = request
+        .user
+        .filter(|user| !user.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_USER.to_string());
+    if user.chars().count() > MAX_ACTOR_LEN {
+        return Err(ApiErr::Invalid {
+            message: format!("user must be at most {} characters", MAX_ACTOR_LEN),
+            fields: vec!["user"],
+        });
+    }
+
+    let step = interpreter.conversations.take(conversation_id);
+    // On error the taken step isn't put back, so the conversation starts over, as in Telegram.
+    let turn = interpreter
+        .engine
+        .handle(&conversation_id, step, user, event)
+        .await
+        .map_err(ApiErr::Internal)?;
+    interpreter.conversations.put(conversation_id, turn.next);
+    Ok(Json(InterpretResponse {
+        conversation_id,
+        transcript: turn.transcript,
+        reply: turn.reply,
+    }))
+}
@@ -0,0 +1,88 @@
+pub mod interpret;
+pub mod tasks;
+
+use crate::db::interface::RepositoryErr;
+use crate::domain::task::service::TaskDataFlows;
+use crate::llm::interface::LLMClient;
+use crate::transcription::interface::TranscriptionClient;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Json, Router};
+use serde_json::json;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Longest actor name the task history can hold (`task_events.actor` is a `VARCHAR(255)`).
+pub const MAX_ACTOR_LEN: usize = 255;
+
+/// The HTTP API. It has no authentication of its own, so only expose it on a trusted network.
+pub fn router<T, L, S>(
+    task_data_flows: Arc<S>,
+    interpreter: Arc<interpret::Interpreter<T, L, S>>,
+) -> Router
+where
+    T: TranscriptionClient + Send + Sync + 'static,
+    L: LLMClient<String> + Send + Sync + 'static,
+    S: TaskDataFlows + Send + Sync + 'static,
+{
+    Router::new()
+        .merge(tasks::router(task_data_flows))
+        .merge(interpret::router(interpreter))
+}
+
+/// Failures surfaced to HTTP clients as a status code and a `{"error": ...}` JSON body.
+#[derive(Debug)]
+pub enum ApiErr {
+    NotFound {
+        id: Uuid,
+    },
+    /// The request was well-formed JSON but can't be applied as it stands.
+    Invalid {
+        message: String,
+        fields: Vec<&'static str>,
+    },
+    Internal(anyhow::Error),
+}
+impl Display for ApiErr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiErr::NotFound { id } => write!(f, "no task found with id {}", id),
+            ApiErr::Invalid { message, .. } => write!(f, "{}", message),
+            ApiErr::Internal(e) => write!(f, "internal error: {}", e),
+        }
+    }
+}
+impl std::error::Error for ApiErr {}
+
+impl From<anyhow::Error> for ApiErr {
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast_ref::<RepositoryErr>() {
+            Some(RepositoryErr::NotFound { id }) => ApiErr::NotFound { id: *id },
+            None => ApiErr::Internal(e),
+        }
+    }
+}
+
+impl IntoResponse for ApiErr {
+    fn into_response(self) -> Response {
+        let (status, body) = match &self {
+            ApiErr::NotFound { .. } => {
+                (StatusCode::NOT_FOUND, json!({ "error": self.to_string() }))
+            }
+            ApiErr::Invalid { message, fields } => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                json!({ "error": message, "fields": fields }),
+            ),
+            ApiErr::Internal(e) => {
+                log::error!("HTTP request failed: {:?}", e);
+                // Storage errors can carry query details, so keep them out of the response.
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    json!({ "error": "internal error" }),
+                )
+            }
+        };
+        (status, Json(body)).into_response()
+    }
+}
@@ -0,0 +1,259 @@
+use super::{ApiErr, MAX_ACTOR_LEN};
+use crate::domain::task::event::ChangeOrigin;
+use crate::domain::task::filter::{SortDirection, SortField, TaskFilter, TaskQuery, TaskSort};
+use crate::domain::task::model::{PartialTask, Task, TaskStatus};
+use crate::domain::task::service::TaskDataFlows;
+use crate::input::parsing_pipeline_steps::intent::Intent;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Recorded as the actor of API changes unless the request names one in this header.
+const ACTOR_HEADER: &str = "x-actor";
+const DEFAULT_ACTOR: &str = "api";
+
+/// `/tasks` CRUD plus filtered listing:
+///
+/// - `GET /tasks` lists tasks matching `ListParams`; `POST /tasks/query` takes a full
+///   `TaskQuery` body for anything the query string can't express.
+/// - `POST /tasks` creates a task from a `PartialTask` body.
+/// - `GET`, `PATCH` and `DELETE /tasks/:id` read, partially update and soft-delete one task.
+pub fn router<S>(task_data_flows: Arc<S>) -> Router
+where
+    S: TaskDataFlows + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/tasks", get(list_tasks::<S>).post(create_task::<S>))
+        .route("/tasks/query", post(query_tasks::<S>))
+        .route(
+            "/tasks/:id",
+            get(get_task::<S>)
+                .patch(update_task::<S>)
+                .delete(delete_task::<S>),
+        )
+        .with_state(task_data_flows)
+}
+
+/// Query-string filters for `GET /tasks`. Every given condition must hold.
+#[derive(Debug, Default, Deserialize)]
+pub struct ListParams {
+    pub assignee: Option<String>,
+    pub status: Option<TaskStatus>,
+    pub due_before: Option<DateTime<Utc>>,
+    pub due_after: Option<DateTime<Utc>>,
+    /// Case-insensitive substring of the description.
+    pub contains: Option<String>,
+    pub sort: Option<SortField>,
+    pub direction: Option<SortDirection>,
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub include_deleted: bool,
+}
+impl From<ListParams> for TaskQuery {
+    fn from(params: ListParams) -> Self {
+        let filters: Vec<TaskFilter> = [
+            params
+                .assignee
+                .map(|assignee| TaskFilter::AssigneeEquals { assignee }),
+            params.status.map(|status| TaskFilter::Status { status }),
+            params
+                .due_before
+                .map(|before| TaskFilter::DueBefore { before }),
+            params.due_after.map(|after| TaskFilter::DueAfter { after }),
+            params
+                .contains
+                .map(|text| TaskFilter::DescriptionContains { text }),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        TaskQuery {
+            filter: (!filters.is_empty()).then_some(TaskFilter::And { filters }),
+            sort: params
+                .sort
+                .map(|field| TaskSort {
+                    field,
+                    direction: params.direction.unwrap_or_default(),
+                })
+                .into_iter()
+                .collect(),
+            limit: params.limit,
+            include_deleted: params.include_deleted,
+        }
+    }
+}
+
+async fn list_tasks<S: TaskDataFlows>(
+    State(task_data_flows): State<Arc<S>>,
+    Query(params): Query<ListParams>,
+) -> Result<Json<Vec<Task>>, ApiErr> {
+    Ok(Json(task_data_flows.retrieve_tasks(params.into()).await?))
+}
+
+async fn query_tasks<S: TaskDataFlows>(
+    State(task_data_flows): State<Arc<S>>,
+    Json(query): Json<TaskQuery>,
+) -> Result<Json<Vec<Task>>, ApiErr> {
+    Ok(Json(task_data_flows.retrieve_tasks(query).await?))
+}
+
+async fn get_task<S: TaskDataFlows>(
+    State(task_data_flows): State<Arc<S>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Task>, ApiErr> {
+    Ok(Json(task_data_flows.retrieve_task(id).await?))
+}
+
+async fn create_task<S: TaskDataFlows>(
+    State(task_data_flows): State<Arc<S>>,
+    headers: HeaderMap,
+    Json(mut fields): Json<PartialTask>,
+) -> Result<impl IntoResponse, ApiErr> {
+    let missing: Vec<&'static str> = [
+        ("description", !has_text(&fields.description)),
+        ("due_date", fields.due_date.is_none()),
+        ("assignee", !has_text(&fields.assignee)),
+    ]
+    .into_iter()
+    .filter_map(|(name, missing)| missing.then_some(name))
+    .collect();
+    if !missing.is_empty() {
+        return Err(ApiErr::Invalid {
+            message: format!("missing required fields: {}", missing.join(", ")),
+            fields: missing,
+        });
+    }
+    fields.create_date = fields.create_date.or(Some(Utc::now()));
+    let origin = origin(&headers, "POST /tasks", Intent::CreateNewTask, &fields)?;
+    let task = task_data_flows.create_new_task(fields, &origin).await?;
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, format!("/tasks/{}", task.id))],
+        Json(task),
+    ))
+}
+
+async fn update_task<S: TaskDataFlows>(
+    State(task_data_flows): State<Arc<S>>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(mut fields): Json<PartialTask>,
+) -> Result<Json<Task>, ApiErr> {
+    if fields.id.is_some_and(|body_id| body_id != id) {
+        return Err(ApiErr::Invalid {
+            message: String::from("the body's id doesn't match the path"),
+            fields: vec!["id"],
+        });
+    }
+    if fields.deleted_at.is_some() {
+        return Err(ApiErr::Invalid {
+            message: String::from("use DELETE to delete a task"),
+            fields: vec!["deleted_at"],
+        });
+    }
+    let managed: Vec<&'static str> = [
+        ("create_date", fields.create_date.is_some()),
+        ("completed_at", fields.completed_at.is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(name, given)| given.then_some(name))
+    .collect();
+    if !managed.is_empty() {
+        return Err(ApiErr::Invalid {
+            message: format!("fields can't be set directly: {}", managed.join(", ")),
+            fields: managed,
+        });
+    }
+    let blank: Vec<&'static str> = [
+        (
+            "description",
+            fields.description.is_some() && !has_text(&fields.description),
+        ),
+        (
+            "assignee",
+            fields.assignee.is_some() && !has_text(&fields.assignee),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(name, blank)| blank.then_some(name))
+    .collect();
+    if !blank.is_empty() {
+        return Err(ApiErr::Invalid {
+            message: format!("fields must not be blank: {}", blank.join(", ")),
+            fields: blank,
+        });
+    }
+    fields.id = Some(id);
+    let origin = origin(
+        &headers,
+        &format!("PATCH /tasks/{}", id),
+        Intent::ModifyExistingTask,
+        &fields,
+    )?;
+    Ok(Json(
+        task_data_flows
+            .modify_existing_task(fields, &origin)
+            .await?,
+    ))
+}
+
+async fn delete_task<S: TaskDataFlows>(
+    State(task_data_flows): State<Arc<S>>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<StatusCode, ApiErr> {
+    let fields = PartialTask {
+        id: Some(id),
+        ..Default::default()
+    };
+    let origin = origin(
+        &headers,
+        &format!("DELETE /tasks/{}", id),
+        Intent::DeleteTask,
+        &fields,
+    )?;
+    task_data_flows
+        .delete_existing_task(fields, &origin)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn has_text(field: &Option<String>) -> bool {
+    field.as_deref().is_some_and(|text| !text.trim().is_empty())
+}
+
+/// API changes go into the same task history as chat ones, with the request line standing in
+/// for the message text.
+fn origin(
+    headers: &HeaderMap,
+    request: &str,
+    intent: Intent,
+    fields: &PartialTask,
+) -> Result<ChangeOrigin, ApiErr> {
+    let actor = headers
+        .get(ACTOR_HEADER)
+        .and_then(|actor| actor.to_str().ok())
+        .filter(|actor| !actor.trim().is_empty())
+        .unwrap_or(DEFAULT_ACTOR);
+    if actor.chars().count() > MAX_ACTOR_LEN {
+        return Err(ApiErr::Invalid {
+            message: format!(
+                "the X-Actor header must be at most {} characters",
+                MAX_ACTOR_LEN
+            ),
+            fields: vec![ACTOR_HEADER],
+        });
+    }
+    Ok(ChangeOrigin {
+        actor: actor.to_string(),
+        source_text: request.to_string(),
+        intent: intent.to_string(),
+        params: serde_json::to_value(fields).unwrap_or_default(),
+    })
+}
@@ -0,0 +1,165 @@
+use crate::db::member::{self, ApprovedVia};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use teloxide::types::{User, UserId};
+
+/// Decides who may talk to the bot. Users get in by being on the allow-list, or by sending the
+/// shared passphrase once; either way they are then remembered as approved members.
+pub struct Gate {
+    passphrase: Option<String>,
+    allow_list: HashSet<UserId>,
+    members: member::Store,
+    /// Users already told they aren't approved, so they get a single refusal rather than one
+    /// per message.
+    refused: Mutex<HashSet<UserId>>,
+}
+
+pub enum Admission {
+    Approved,
+    /// The message was the passphrase; the user is approved from now on.
+    Unlocked,
+    /// Not approved; the first such message gets a refusal.
+    Refused {
+        first_time: bool,
+    },
+}
+
+impl Gate {
+    pub fn new(
+        passphrase: Option<String>,
+        allow_list: HashSet<UserId>,
+        members: member::Store,
+    ) -> Self {
+        if passphrase.is_none() && allow_list.is_empty() {
+            log::warn!("auth.open is set: the bot is open to everyone");
+        }
+        Self {
+            passphrase,
+            allow_list,
+            members,
+            refused: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        self.passphrase.is_none() && self.allow_list.is_empty()
+    }
+
+    /// Whether `user` may use the bot, without side effects.
+    pub async fn is_approved(&self, user: Option<&User>) -> anyhow::Result<bool> {
+        if self.is_open() {
+            return Ok(true);
+        }
+        let Some(user) = user else {
+            return Ok(false);
+        };
+        if self.allow_list.contains(&user.id) {
+            return Ok(true);
+        }
+        self.members.is_approved(user.id).await
+    }
+
+    /// Records everyone on the allow-list as an approved member and revokes the allow-list
+    /// approvals of anyone since taken off it. Passphrase approvals are kept.
+    pub async fn sync_allow_list(&self) -> anyhow::Result<()> {
+        for user_id in &self.allow_list {
+            self.members
+                .approve(*user_id, ApprovedVia::AllowList)
+                .await?;
+        }
+        let keep: Vec<UserId> = self.allow_list.iter().copied().collect();
+        let revoked = self.members.revoke_allow_list_except(&keep).await?;
+        if revoked > 0 {
+            log::info!("Revoked {} members no longer on the allow-list", revoked);
+        }
+        Ok(())
+    }
+
+    /// Checks an incoming message from a user who isn't approved yet, unlocking them if it is
+    /// the passphrase.
+    pub async fn admit(
+        &self,
+        user: Option<&User>,
+        text: Option<&str>,
+    ) -> anyhow::Result<Admission> {
+        if self.is_approved(user).await? {
+            return Ok(Admission::Approved);
+        }
+        let Some(user) = user else {
+            return Ok(Admission::Refused { first_time: false });
+        };
+        let is_passphrase = matches!(
+            (&self.passphrase, text),
+            (Some(passphrase), Some(text)) if text.trim() == passphrase
+        );
+        if is_passphrase {
+            self.members
+                .approve(user.id, ApprovedVia::Passphrase)
+                .await?;
+            self.refused
+                .lock()
+                .expect("refused users lock poisoned")
+                .remove(&user.id);
+            return Ok(Admission::Unlocked);
+        }
+        let first_time = self
+            .refused
+            .lock()
+            .expect("refused users lock poisoned")
+            .insert(user.id);
+        Ok(Admission::Refused { first_time })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    fn user(id: u64) -> User {
+        User {
+            id: UserId(id),
+            is_bot: false,
+            first_name: format!("User {}", id),
+            last_name: None,
+            username: None,
+            language_code: None,
+            is_premium: false,
+            added_to_attachment_menu: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn taking_someone_off_the_allow_list_revokes_their_access() {
+        // What an earlier run with users 1 and 2 on the allow-list left behind, plus user 3 who
+        // unlocked the bot with the passphrase.
+        let members = member::Store::new(Database::Memory);
+        members
+            .approve(UserId(1), ApprovedVia::AllowList)
+            .await
+            .unwrap();
+        members
+            .approve(UserId(2), ApprovedVia::AllowList)
+            .await
+            .unwrap();
+        members
+            .approve(UserId(3), ApprovedVia::Passphrase)
+            .await
+            .unwrap();
+        let gate = Gate::new(
+            Some(String::from("let me in")),
+            HashSet::from([UserId(1)]),
+            members,
+        );
+
+        gate.sync_allow_list().await.unwrap();
+
+        assert!(gate.is_approved(Some(&user(1))).await.unwrap());
+        assert!(!gate.is_approved(Some(&user(2))).await.unwrap());
+        assert!(gate.is_approved(Some(&user(3))).await.unwrap());
+        assert!(matches!(
+            gate.admit(Some(&user(2)), Some("hello")).await.unwrap(),
+            Admission::Refused { first_time: true }
+        ));
+    }
+}
@@ -0,0 +1,713 @@
+use crate::digest;
+use crate::execution::ConfirmationPolicy;
+use crate::input::parsing_pipeline_steps::intent::Intent;
+use crate::output::reply::Renderer;
+use chrono::NaiveTime;
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::env;
+use std::fmt::{Display, Formatter};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+/// Read when `CONFIG_PATH` is unset. A missing default file is fine; everything can come from
+/// the environment.
+pub const DEFAULT_CONFIG_PATH: &str = "housekeeping.toml";
+
+/// Everything the bot needs at startup. Loaded from a TOML file, then overridden field by field
+/// from the environment (see `apply_env`), then validated as a whole.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub telegram: TelegramConfig,
+    pub database: DatabaseConfig,
+    pub llm: LlmConfig,
+    pub transcription: TranscriptionConfig,
+    /// IANA name, e.g. `Europe/London`. Due dates are read and shown in this zone.
+    pub timezone: Tz,
+    pub auth: AuthConfig,
+    pub reply: ReplyConfig,
+    pub confirmation: ConfirmationConfig,
+    pub http: HttpConfig,
+    pub reminders: ReminderConfig,
+    pub digests: DigestConfig,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            telegram: TelegramConfig::default(),
+            database: DatabaseConfig::default(),
+            llm: LlmConfig::default(),
+            transcription: TranscriptionConfig::default(),
+            timezone: Tz::UTC,
+            auth: AuthConfig::default(),
+            reply: ReplyConfig::default(),
+            confirmation: ConfirmationConfig::default(),
+            http: HttpConfig::default(),
+            reminders: ReminderConfig::default(),
+            digests: DigestConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelegramConfig {
+    pub bot_token: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    /// `postgres://…`, `sqlite:…` or `memory:`.
+    pub url: String,
+    /// Half-finished conversations older than this are dropped.
+    pub dialogue_ttl_hours: i64,
+}
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            dialogue_ttl_hours: 24,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LlmBackend {
+    #[default]
+    OpenAI,
+    Ollama,
+}
+
+/// Which model serves one stage of the pipeline. Unset fields fall back to `LlmConfig::default`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LlmStageConfig {
+    pub backend: Option<LlmBackend>,
+    pub model: Option<String>,
+    /// Server root for the Ollama backend.
+    pub base_url: Option<String>,
+    /// Whether the Ollama backend asks for JSON-format output. On unless set to false.
+    pub json_mode: Option<bool>,
+}
+impl LlmStageConfig {
+    fn or(&self, fallback: &LlmStageConfig) -> LlmStageConfig {
+        LlmStageConfig {
+            backend: self.backend.or(fallback.backend),
+            model: self.model.clone().or_else(|| fallback.model.clone()),
+            base_url: self.base_url.clone().or_else(|| fallback.base_url.clone()),
+            json_mode: self.json_mode.or(fallback.json_mode),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PipelineStage {
+    Intent,
+    Params,
+    Reply,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LlmConfig {
+    pub system_prompt: String,
+    /// Used for every stage that doesn't override it.
+    pub default: LlmStageConfig,
+    pub intent: LlmStageConfig,
+    pub params: LlmStageConfig,
+    pub reply: LlmStageConfig,
+}
+impl Default for LlmConfig {
+    fn default() -> Self {
+        Self {
+            system_prompt: String::from(
+                r#"You are managing a task assignment system. Provide all response as JSON objects only.
+In the event of errors or uncertain outcome, return the empty JSON object."#,
+            ),
+            default: LlmStageConfig::default(),
+            intent: LlmStageConfig::default(),
+            params: LlmStageConfig::default(),
+            reply: LlmStageConfig::default(),
+        }
+    }
+}
+impl LlmConfig {
+    /// The stage's settings with the defaults filled in.
+    pub fn stage(&self, stage: PipelineStage) -> LlmStageConfig {
+        match stage {
+            PipelineStage::Intent => self.intent.or(&self.default),
+            PipelineStage::Params => self.params.or(&self.default),
+            PipelineStage::Reply => self.reply.or(&self.default),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptionBackend {
+    #[default]
+    OpenAI,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TranscriptionConfig {
+    pub backend: TranscriptionBackend,
+    pub model: String,
+}
+impl Default for TranscriptionConfig {
+    fn default() -> Self {
+        Self {
+            backend: TranscriptionBackend::OpenAI,
+            model: String::from("whisper-1"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Sending this unlocks the bot for the sender.
+    pub passphrase: Option<String>,
+    /// Telegram user ids approved without a passphrase.
+    pub allowed_user_ids: Vec<u64>,
+    /// Let everyone in when there is neither a passphrase nor an allow-list. Without it, such a
+    /// config is rejected rather than leaving the bot (and its LLM calls) open to strangers.
+    pub open: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReplyConfig {
+    pub renderer: Renderer,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfirmationConfig {
+    /// Intents that need a Confirm tap before running. Unset keeps the default policy; an empty
+    /// list turns confirmation off.
+    pub intents: Option<Vec<Intent>>,
+}
+impl ConfirmationConfig {
+    pub fn policy(&self) -> ConfirmationPolicy {
+        match &self.intents {
+            Some(intents) => ConfirmationPolicy::new(intents.clone()),
+            None => ConfirmationPolicy::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Serve the REST API next to the Telegram bot. Off unless asked for, since it has no
+    /// authentication.
+    pub enabled: bool,
+    /// The API has no authentication, so keep this on a trusted interface.
+    pub listen: SocketAddr,
+}
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: SocketAddr::from(([127, 0, 0, 1], 8080)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReminderConfig {
+    /// Message assignees in their linked chat (see `/remindme`) as tasks come due.
+    pub enabled: bool,
+    /// How long before the due date each reminder goes out.
+    pub offsets_minutes: Vec<i64>,
+    /// How often to look for tasks that are coming due.
+    pub poll_interval_secs: u64,
+}
+impl Default for ReminderConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            offsets_minutes: vec![24 * 60, 60],
+            poll_interval_secs: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DigestConfig {
+    /// Send the daily and weekly digests. Each chat sets its own schedule with `/digest`.
+    pub enabled: bool,
+    /// `HH:MM` in the configured timezone. Chats that link an assignee with `/remindme` get the
+    /// daily digest at this time until they pick another.
+    pub daily_at: String,
+    /// `HH:MM` for `/digest weekly` without a time.
+    pub weekly_at: String,
+    /// How often to look for digests that are due.
+    pub poll_interval_secs: u64,
+}
+impl Default for DigestConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            daily_at: String::from("08:00"),
+            weekly_at: String::from("18:00"),
+            poll_interval_secs: 60,
+        }
+    }
+}
+impl DigestConfig {
+    pub fn daily_time(&self) -> NaiveTime {
+        digest::parse_send_time(&self.daily_at).expect("digests.daily_at is validated")
+    }
+
+    pub fn weekly_time(&self) -> NaiveTime {
+        digest::parse_send_time(&self.weekly_at).expect("digests.weekly_at is validated")
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigErr {
+    Read {
+        path: PathBuf,
+        e: std::io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        e: toml::de::Error,
+    },
+    /// An environment override couldn't be parsed.
+    Env {
+        var: &'static str,
+        message: String,
+    },
+    /// Every problem found by `validate`, so they can all be fixed in one go.
+    Invalid(Vec<String>),
+}
+impl Display for ConfigErr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigErr::Read { path, e } => {
+                write!(f, "could not read config file {}: {}", path.display(), e)
+            }
+            ConfigErr::Parse { path, e } => {
+                write!(f, "invalid config file {}: {}", path.display(), e)
+            }
+            ConfigErr::Env { var, message } => write!(f, "invalid {}: {}", var, message),
+            ConfigErr::Invalid(problems) => {
+                write!(f, "invalid configuration:")?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+impl std::error::Error for ConfigErr {}
+
+impl Config {
+    /// Reads the file named by `CONFIG_PATH` (or `DEFAULT_CONFIG_PATH` if it exists), then
+    /// applies environment overrides. Call `validate` before using the result.
+    pub fn load() -> Result<Self, ConfigErr> {
+        let mut config = match env::var("CONFIG_PATH") {
+            Ok(path) => Self::from_file(Path::new(&path))?,
+            Err(_) if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_PATH))?
+            }
+            Err(_) => Self::default(),
+        };
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    fn from_file(path: &Path) -> Result<Self, ConfigErr> {
+        let contents = std::fs::read_to_string(path).map_err(|e| ConfigErr::Read {
+            path: path.to_path_buf(),
+            e,
+        })?;
+        toml::from_str(&contents).map_err(|e| ConfigErr::Parse {
+            path: path.to_path_buf(),
+            e,
+        })
+    }
+
+    /// Environment variables win over the file: `TELEGRAM_BOT_TOKEN`, `DATABASE_URL`,
+    /// `DIALOGUE_TTL_HOURS`, `LLM_BACKEND`, `LLM_MODEL`, `OLLAMA_URL`, `LLM_<STAGE>_BACKEND` and
+    /// `LLM_<STAGE>_MODEL` for the `INTENT`, `PARAMS` and `REPLY` stages, `TRANSCRIPTION_BACKEND`,
+    /// `TRANSCRIPTION_MODEL`, `TIMEZONE`, `AUTH_PASSPHRASE`, `AUTH_ALLOWED_USER_IDS`
+    /// (comma-separated), `AUTH_OPEN`, `REPLY_RENDERER`, `CONFIRM_INTENTS` (comma-separated),
+    /// `HTTP_ENABLED`, `HTTP_LISTEN`, `REMINDERS_ENABLED`, `REMINDER_OFFSETS_MINUTES`
+    /// (comma-separated), `REMINDER_POLL_INTERVAL_SECS`, `DIGESTS_ENABLED`, `DIGEST_DAILY_AT`,
+    /// `DIGEST_WEEKLY_AT` and `DIGEST_POLL_INTERVAL_SECS`.
+    fn apply_env(&mut self) -> Result<(), ConfigErr> {
+        if let Ok(token) = env::var("TELEGRAM_BOT_TOKEN") {
+            self.telegram.bot_token = token;
+        }
+        if let Ok(url) = env::var("DATABASE_URL") {
+            self.database.url = url;
+        }
+        if let Some(hours) = parse_env("DIALOGUE_TTL_HOURS", |hours| hours.parse().ok())? {
+            self.database.dialogue_ttl_hours = hours;
+        }
+        if let Some(backend) = parse_env("LLM_BACKEND", |name| from_name(name))? {
+            self.llm.default.backend = Some(backend);
+        }
+        if let Ok(model) = env::var("LLM_MODEL") {
+            self.llm.default.model = Some(model);
+        }
+        if let Ok(url) = env::var("OLLAMA_URL") {
+            self.llm.default.base_url = Some(url);
+        }
+        for (stage, backend_var, model_var) in [
+            (
+                &mut self.llm.intent,
+                "LLM_INTENT_BACKEND",
+                "LLM_INTENT_MODEL",
+            ),
+            (
+                &mut self.llm.params,
+                "LLM_PARAMS_BACKEND",
+                "LLM_PARAMS_MODEL",
+            ),
+            (&mut self.llm.reply, "LLM_REPLY_BACKEND", "LLM_REPLY_MODEL"),
+        ] {
+            if let Some(backend) = parse_env(backend_var, |name| from_name(name))? {
+                stage.backend = Some(backend);
+            }
+            if let Ok(model) = env::var(model_var) {
+                stage.model = Some(model);
+            }
+        }
+        if let Some(backend) = parse_env("TRANSCRIPTION_BACKEND", |name| from_name(name))? {
+            self.transcription.backend = backend;
+        }
+        if let Ok(model) = env::var("TRANSCRIPTION_MODEL") {
+            self.transcription.model = model;
+        }
+        if let Some(timezone) = parse_env("TIMEZONE", |name| name.parse().ok())? {
+            self.timezone = timezone;
+        }
+        if let Ok(passphrase) = env::var("AUTH_PASSPHRASE") {
+            self.auth.passphrase = Some(passphrase);
+        }
+        if let Some(ids) = parse_env("AUTH_ALLOWED_USER_IDS", |ids| {
+            split_list(ids).map(|id| id.parse().ok()).collect()
+        })? {
+            self.auth.allowed_user_ids = ids;
+        }
+        if let Some(open) = parse_env("AUTH_OPEN", |open| open.parse().ok())? {
+            self.auth.open = open;
+        }
+        if let Some(renderer) = parse_env("REPLY_RENDERER", |name| from_name(name))? {
+            self.reply.renderer = renderer;
+        }
+        if let Some(intents) = parse_env("CONFIRM_INTENTS", |names| {
+            split_list(names).map(from_name).collect()
+        })? {
+            self.confirmation.intents = Some(intents);
+        }
+        if let Some(enabled) = parse_env("HTTP_ENABLED", |enabled| enabled.parse().ok())? {
+            self.http.enabled = enabled;
+        }
+        if let Some(listen) = parse_env("HTTP_LISTEN", |listen| listen.parse().ok())? {
+            self.http.listen = listen;
+        }
+        if let Some(enabled) = parse_env("REMINDERS_ENABLED", |enabled| enabled.parse().ok())? {
+            self.reminders.enabled = enabled;
+        }
+        if let Some(offsets) = parse_env("REMINDER_OFFSETS_MINUTES", |offsets| {
+            split_list(offsets)
+                .map(|offset| offset.parse().ok())
+                .collect()
+        })? {
+            self.reminders.offsets_minutes = offsets;
+        }
+        if let Some(secs) = parse_env("REMINDER_POLL_INTERVAL_SECS", |secs| secs.parse().ok())? {
+            self.reminders.poll_interval_secs = secs;
+        }
+        if let Some(enabled) = parse_env("DIGESTS_ENABLED", |enabled| enabled.parse().ok())? {
+            self.digests.enabled = enabled;
+        }
+        if let Ok(daily_at) = env::var("DIGEST_DAILY_AT") {
+            self.digests.daily_at = daily_at;
+        }
+        if let Ok(weekly_at) = env::var("DIGEST_WEEKLY_AT") {
+            self.digests.weekly_at = weekly_at;
+        }
+        if let Some(secs) = parse_env("DIGEST_POLL_INTERVAL_SECS", |secs| secs.parse().ok())? {
+            self.digests.poll_interval_secs = secs;
+        }
+        Ok(())
+    }
+
+    /// The settings needed to open the database, which is all `--migrate-only` and
+    /// `--export-events` use.
+    pub fn validate_database(&self) -> Result<(), ConfigErr> {
+        let problems = self.database_problems();
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigErr::Invalid(problems))
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigErr> {
+        let mut problems = self.database_problems();
+        let token = self.telegram.bot_token.trim();
+        if token.is_empty() {
+            problems.push(String::from(
+                "telegram.bot_token is not set (or set TELEGRAM_BOT_TOKEN)",
+            ));
+        } else if !token.contains(':') {
+            problems.push(String::from(
+                "telegram.bot_token doesn't look like a bot token (expected `<id>:<secret>`)",
+            ));
+        }
+        let uses_openai = [
+            PipelineStage::Intent,
+            PipelineStage::Params,
+            PipelineStage::Reply,
+        ]
+        .into_iter()
+        .any(|stage| self.llm.stage(stage).backend.unwrap_or_default() == LlmBackend::OpenAI)
+            || self.transcription.backend == TranscriptionBackend::OpenAI;
+        if uses_openai && env::var("OPENAI_API_KEY").map_or(true, |key| key.trim().is_empty()) {
+            problems.push(String::from(
+                "OPENAI_API_KEY must be set in the environment for the OpenAI backends",
+            ));
+        }
+        if self.transcription.model.trim().is_empty() {
+            problems.push(String::from("transcription.model must not be empty"));
+        }
+        if let Some(passphrase) = &self.auth.passphrase {
+            if passphrase.trim().is_empty() {
+                problems.push(String::from(
+                    "auth.passphrase is empty; remove it to disable passphrase unlocking",
+                ));
+            }
+        }
+        if self.auth.passphrase.is_none()
+            && self.auth.allowed_user_ids.is_empty()
+            && !self.auth.open
+        {
+            problems.push(String::from(
+                "auth needs a passphrase or allowed_user_ids; set auth.open = true to let everyone in",
+            ));
+        }
+        if self
+            .reminders
+            .offsets_minutes
+            .iter()
+            .any(|offset| *offset <= 0)
+        {
+            problems.push(String::from(
+                "reminders.offsets_minutes must all be positive",
+            ));
+        }
+        if self.reminders.poll_interval_secs == 0 {
+            problems.push(String::from(
+                "reminders.poll_interval_secs must be positive",
+            ));
+        }
+        if self.digests.poll_interval_secs == 0 {
+            problems.push(String::from("digests.poll_interval_secs must be positive"));
+        }
+        for (field, value) in [
+            ("digests.daily_at", &self.digests.daily_at),
+            ("digests.weekly_at", &self.digests.weekly_at),
+        ] {
+            if digest::parse_send_time(value).is_none() {
+                problems.push(format!("{} `{}` should be a time like 08:00", field, value));
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigErr::Invalid(problems))
+        }
+    }
+
+    fn database_problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        let url = self.database.url.trim();
+        if url.is_empty() {
+            problems.push(String::from(
+                "database.url is not set (or set DATABASE_URL)",
+            ));
+        } else if !["postgres://", "postgresql://", "sqlite:", "memory:"]
+            .iter()
+            .any(|scheme| url.starts_with(scheme))
+        {
+            problems.push(format!(
+                "database.url `{}` should start with postgres://, sqlite: or memory:",
+                url
+            ));
+        }
+        if self.database.dialogue_ttl_hours <= 0 {
+            problems.push(String::from("database.dialogue_ttl_hours must be positive"));
+        }
+        problems
+    }
+}
+
+fn parse_env<T>(
+    var: &'static str,
+    parse: impl FnOnce(&str) -> Option<T>,
+) -> Result<Option<T>, ConfigErr> {
+    match env::var(var) {
+        Ok(value) => parse(value.trim()).map(Some).ok_or(ConfigErr::Env {
+            var,
+            message: format!("could not parse `{}`", value),
+        }),
+        Err(_) => Ok(None),
+    }
+}
+
+fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+/// Parses an enum from the name its config file form uses.
+fn from_name<T: serde::de::DeserializeOwned>(name: &str) -> Option<T> {
+    serde_json::from_value(serde_json::Value::String(name.to_string())).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// The environment is shared by every test thread, so tests that touch it take turns.
+    static ENV: Mutex<()> = Mutex::new(());
+
+    const VALID: &str = r#"
+        [telegram]
+        bot_token = "123456789:secret"
+
+        [database]
+        url = "memory:"
+
+        [auth]
+        passphrase = "open sesame"
+    "#;
+
+    fn parse(toml: &str) -> Config {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn with_env<R>(vars: &[(&str, &str)], f: impl FnOnce() -> R) -> R {
+        let _guard = ENV.lock().unwrap_or_else(|e| e.into_inner());
+        for (var, value) in vars {
+            env::set_var(var, value);
+        }
+        let result = f();
+        for (var, _) in vars {
+            env::remove_var(var);
+        }
+        result
+    }
+
+    fn problems(config: &Config) -> Vec<String> {
+        match with_env(&[("OPENAI_API_KEY", "sk-test")], || config.validate()) {
+            Ok(()) => vec![],
+            Err(ConfigErr::Invalid(problems)) => problems,
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn environment_overrides_the_file() {
+        let mut config = parse(VALID);
+        with_env(
+            &[
+                ("LLM_PARAMS_BACKEND", "ollama"),
+                ("LLM_PARAMS_MODEL", "llama3"),
+                ("TRANSCRIPTION_BACKEND", "openai"),
+                ("REMINDER_OFFSETS_MINUTES", "30, 10"),
+                ("REMINDER_POLL_INTERVAL_SECS", "15"),
+                ("DIGEST_POLL_INTERVAL_SECS", "300"),
+            ],
+            || config.apply_env(),
+        )
+        .unwrap();
+
+        let params = config.llm.stage(PipelineStage::Params);
+        assert_eq!(params.backend, Some(LlmBackend::Ollama));
+        assert_eq!(params.model.as_deref(), Some("llama3"));
+        assert_eq!(config.llm.stage(PipelineStage::Intent).backend, None);
+        assert_eq!(config.transcription.backend, TranscriptionBackend::OpenAI);
+        assert_eq!(config.reminders.offsets_minutes, vec![30, 10]);
+        assert_eq!(config.reminders.poll_interval_secs, 15);
+        assert_eq!(config.digests.poll_interval_secs, 300);
+    }
+
+    #[test]
+    fn unparseable_overrides_name_the_variable() {
+        let mut config = parse(VALID);
+        let result = with_env(&[("LLM_REPLY_BACKEND", "claude")], || config.apply_env());
+
+        assert!(matches!(
+            result,
+            Err(ConfigErr::Env {
+                var: "LLM_REPLY_BACKEND",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn a_complete_config_is_valid() {
+        assert_eq!(problems(&parse(VALID)), Vec::<String>::new());
+    }
+
+    #[test]
+    fn validate_reports_every_problem() {
+        let config = parse(
+            r#"
+            [telegram]
+            bot_token = "secret"
+
+            [database]
+            url = "mysql://localhost/housekeeping"
+
+            [reminders]
+            offsets_minutes = [60, 0]
+            poll_interval_secs = 0
+
+            [digests]
+            daily_at = "8am"
+        "#,
+        );
+
+        assert_eq!(
+            problems(&config),
+            vec![
+                "database.url `mysql://localhost/housekeeping` should start with postgres://, sqlite: or memory:",
+                "telegram.bot_token doesn't look like a bot token (expected `<id>:<secret>`)",
+                "auth needs a passphrase or allowed_user_ids; set auth.open = true to let everyone in",
+                "reminders.offsets_minutes must all be positive",
+                "reminders.poll_interval_secs must be positive",
+                "digests.daily_at `8am` should be a time like 08:00",
+            ]
+        );
+    }
+
+    #[test]
+    fn an_open_bot_must_be_asked_for() {
+        let mut config = parse(VALID);
+        config.auth.passphrase = None;
+        assert_eq!(problems(&config).len(), 1);
+
+        config.auth.open = true;
+        assert_eq!(problems(&config), Vec::<String>::new());
+    }
+}
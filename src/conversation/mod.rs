@@ -0,0 +1,995 @@
+use crate::domain::task::model::Task;
+use crate::domain::task::service::TaskDataFlows;
+use crate::execution::{
+    self, undo::UndoHistory, ConfirmationPolicy, ExecutionErr, Outcome, Requester, SuccessReport,
+};
+use crate::input::parsing_pipeline::{self, InputParseErr};
+use crate::input::parsing_pipeline_steps::intent::Intent;
+use crate::input::parsing_pipeline_steps::params::{self, Extraction};
+use crate::llm::interface::LLMClient;
+use crate::output::reply::{self, Renderer};
+use crate::transcription::interface::TranscriptionClient;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::hash::Hash;
+use uuid::Uuid;
+
+/// Where a conversation is between messages. Transports persist this (Telegram in its dialogue
+/// storage, HTTP by conversation id) and hand it back with the next event.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub enum InteractionSteps {
+    #[default]
+    ReceiveInput,
+    ValidateParams {
+        input_log: Vec<String>,
+        intent: Intent,
+        params: Option<Extraction>,
+    },
+    /// The user's description matched several tasks; waiting for them to pick one by number.
+    ChooseTask {
+        intent: Intent,
+        params: Extraction,
+        candidates: Vec<Task>,
+        requester: Requester,
+    },
+    /// A resolved operation is waiting for the user to confirm or cancel it. `params` already
+    /// carries the target task's id.
+    AwaitConfirmation {
+        intent: Intent,
+        params: Extraction,
+        requester: Requester,
+        /// Fresh for each operation, so an answer to an older preview can be told apart.
+        nonce: Uuid,
+    },
+}
+
+/// Something the user sent, stripped of transport details.
+pub enum Inbound {
+    Text(String),
+    /// A voice note or uploaded recording; transcribed before anything else happens.
+    Audio(Vec<u8>),
+    /// `nonce` is the one from the `Reply::Confirm` being answered. A press for any other
+    /// operation is refused.
+    Button {
+        button: Button,
+        nonce: Uuid,
+    },
+}
+
+/// Answers to a `Reply::Confirm`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Button {
+    Confirm,
+    Cancel,
+}
+
+/// What to tell the user. Transports decide how to show it: Telegram as messages and keyboards,
+/// HTTP as this JSON.
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Reply {
+    /// The operation ran. For non-empty task lists `reply` is the plain template, since
+    /// transports usually render the list themselves.
+    Done {
+        report: SuccessReport<Outcome>,
+        reply: String,
+    },
+    /// Some required params are missing; the answer goes to the same conversation.
+    MissingParams {
+        intent: Intent,
+        question: String,
+        missing_fields: Vec<&'static str>,
+    },
+    /// Several tasks match; the answer is the number of the intended one, counting from 1.
+    ChooseTask {
+        intent: Intent,
+        question: String,
+        candidates: Vec<Task>,
+    },
+    /// The operation needs a `Button::Confirm` (or "yes") before it runs. The answer should
+    /// carry `nonce` back.
+    Confirm {
+        intent: Intent,
+        question: String,
+        nonce: Uuid,
+    },
+    Cancelled {
+        message: String,
+    },
+    /// No intent could be identified; the conversation starts over.
+    NotUnderstood {
+        message: String,
+    },
+    /// The request was understood but can't be carried out, e.g. no task matches it.
+    Failed {
+        message: String,
+    },
+}
+
+/// The result of one inbound event.
+pub struct Turn {
+    pub reply: Reply,
+    pub next: InteractionSteps,
+    /// What the audio was heard as, if the event was audio.
+    pub transcript: Option<String>,
+}
+impl Turn {
+    fn new(reply: Reply, next: InteractionSteps) -> Self {
+        Self {
+            reply,
+            next,
+            transcript: None,
+        }
+    }
+}
+
+/// The dialogue logic shared by every transport: transcription, intent and param extraction,
+/// clarification, task choice, confirmation and execution. `K` identifies a conversation for the
+/// undo history.
+pub struct Engine<T, L, S, K: Eq + Hash> {
+    pub transcription_client: T,
+    /// One client per pipeline stage, so each can use its own backend and model.
+    pub intent_llm: L,
+    pub params_llm: L,
+    pub reply_llm: L,
+    pub task_data_flows: S,
+    pub confirmation_policy: ConfirmationPolicy,
+    pub reply_renderer: Renderer,
+    /// Due dates are interpreted and shown in this timezone.
+    pub timezone: Tz,
+    pub undo_history: UndoHistory<K>,
+}
+
+impl<T, L, S, K> Engine<T, L, S, K>
+where
+    T: TranscriptionClient,
+    L: LLMClient<String>,
+    S: TaskDataFlows,
+    K: Eq + Hash + Clone,
+{
+    /// Advances `conversation` from `step` on `event`, sent by `user`. Errors are failures the
+    /// user can't fix by answering differently (storage, transcription, or a bug); transports
+    /// should report them and start the conversation over.
+    pub async fn handle(
+        &self,
+        conversation: &K,
+        step: InteractionSteps,
+        user: String,
+        event: Inbound,
+    ) -> anyhow::Result<Turn> {
+        let text = match event {
+            Inbound::Text(text) => text,
+            Inbound::Audio(audio) => {
+                let transcript = self.transcription_client.transcribe(audio).await?;
+                let mut turn = self
+                    .handle_text(conversation, step, user, transcript.clone())
+                    .await?;
+                turn.transcript = Some(transcript);
+                return Ok(turn);
+            }
+            Inbound::Button { button, nonce } => {
+                return self.press(conversation, step, user, button, nonce).await
+            }
+        };
+        self.handle_text(conversation, step, user, text).await
+    }
+
+    /// Runs an operation whose params are already complete and exact (a task id rather than a
+    /// description), skipping confirmation. For buttons and commands that name a task directly.
+    pub async fn execute(
+        &self,
+        conversation: &K,
+        requester: Requester,
+        intent: Intent,
+        params: Extraction,
+    ) -> anyhow::Result<Turn> {
+        let outcome = match intent {
+            Intent::Undo => {
+                execution::undo_last(
+                    &self.undo_history,
+                    conversation,
+                    &requester,
+                    &self.task_data_flows,
+                )
+                .await
+            }
+            _ => execution::resolve(intent, params, &requester, &self.task_data_flows).await,
+        };
+        match outcome {
+            Ok(mut report) => {
+                if let Some(undo) = report.undo.take() {
+                    self.undo_history.push(conversation.clone(), undo);
+                }
+                let reply = match &report.outcome {
+                    Outcome::Tasks(tasks) if !tasks.tasks().is_empty() => {
+                        reply::template(&report, self.timezone)
+                    }
+                    _ => {
+                        reply::render(self.reply_renderer, &self.reply_llm, &report, self.timezone)
+                            .await
+                    }
+                };
+                Ok(Turn::new(
+                    Reply::Done { report, reply },
+                    InteractionSteps::ReceiveInput,
+                ))
+            }
+            Err(e) => execution_err(requester, e),
+        }
+    }
+
+    async fn handle_text(
+        &self,
+        conversation: &K,
+        step: InteractionSteps,
+        user: String,
+        text: String,
+    ) -> anyhow::Result<Turn> {
+        let wants_cancel = matches!(text.trim().to_lowercase().as_str(), "cancel" | "/cancel");
+        match step {
+            InteractionSteps::ReceiveInput => self.receive_input(conversation, text, user).await,
+            _ if wants_cancel => Ok(Turn::new(
+                Reply::Cancelled {
+                    message: String::from("Okay, I've dropped that request."),
+                },
+                InteractionSteps::ReceiveInput,
+            )),
+            InteractionSteps::ValidateParams {
+                input_log,
+                intent,
+                params,
+            } => {
+                self.validate_params(conversation, (input_log, intent, params), text, user)
+                    .await
+            }
+            InteractionSteps::ChooseTask {
+                intent,
+                params,
+                candidates,
+                requester,
+            } => {
+                let choice = text
+                    .trim()
+                    .trim_end_matches('.')
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| n.checked_sub(1))
+                    .and_then(|i| candidates.get(i));
+                match choice {
+                    Some(chosen) => {
+                        let params = params.with_target_id(chosen.id);
+                        self.dispatch(conversation, requester, intent, params).await
+                    }
+                    None => Ok(Turn::new(
+                        Reply::ChooseTask {
+                            intent: intent.clone(),
+                            question: format!(
+                                "Please reply with a number between 1 and {}.",
+                                candidates.len()
+                            ),
+                            candidates: candidates.clone(),
+                        },
+                        InteractionSteps::ChooseTask {
+                            intent,
+                            params,
+                            candidates,
+                            requester,
+                        },
+                    )),
+                }
+            }
+            InteractionSteps::AwaitConfirmation {
+                intent,
+                params,
+                requester,
+                nonce,
+            } => {
+                let button = match text.trim().to_lowercase().as_str() {
+                    "yes" | "y" | "confirm" => Some(Button::Confirm),
+                    "no" | "n" => Some(Button::Cancel),
+                    _ => None,
+                };
+                let step = InteractionSteps::AwaitConfirmation {
+                    intent: intent.clone(),
+                    params,
+                    requester,
+                    nonce,
+                };
+                match button {
+                    Some(button) => self.press(conversation, step, user, button, nonce).await,
+                    None => Ok(Turn::new(
+                        Reply::Confirm {
+                            intent,
+                            question: String::from(
+                                "Please confirm or cancel the change above first.",
+                            ),
+                            nonce,
+                        },
+                        step,
+                    )),
+                }
+            }
+        }
+    }
+
+    async fn press(
+        &self,
+        conversation: &K,
+        step: InteractionSteps,
+        user: String,
+        button: Button,
+        nonce: Uuid,
+    ) -> anyhow::Result<Turn> {
+        let pending = match &step {
+            InteractionSteps::AwaitConfirmation { nonce, .. } => *nonce,
+            _ => return Ok(Turn::new(not_pending(), step)),
+        };
+        if nonce != pending {
+            return Ok(Turn::new(
+                Reply::Failed {
+                    message: String::from(
+                        "That request has been replaced by a newer one. Please answer the latest.",
+                    ),
+                },
+                step,
+            ));
+        }
+        let InteractionSteps::AwaitConfirmation {
+            intent,
+            params,
+            mut requester,
+            ..
+        } = step
+        else {
+            unreachable!("the step was matched as AwaitConfirmation above");
+        };
+        match button {
+            Button::Confirm => {
+                // Whoever confirmed is the one answerable for the change.
+                requester.user = user;
+                self.execute(conversation, requester, intent, params).await
+            }
+            Button::Cancel => Ok(Turn::new(
+                Reply::Cancelled {
+                    message: String::from("Okay, I've left it as it was."),
+                },
+                InteractionSteps::ReceiveInput,
+            )),
+        }
+    }
+
+    async fn receive_input(
+        &self,
+        conversation: &K,
+        text: String,
+        user: String,
+    ) -> anyhow::Result<Turn> {
+        match parsing_pipeline::from_text(&text, &self.intent_llm, &self.params_llm, self.timezone)
+            .await
+        {
+            Ok((intent, params)) => {
+                self.ask_or_dispatch(conversation, vec![text], user, intent, params)
+                    .await
+            }
+            Err(InputParseErr::IntentErr(e)) => Ok(Turn::new(
+                Reply::NotUnderstood {
+                    message: format!(
+                        "I didn't really understand what you were looking for there ({}).",
+                        e
+                    ),
+                },
+                InteractionSteps::ReceiveInput,
+            )),
+            Err(InputParseErr::ParamsErr(_, intent, extraction_attempt)) => {
+                let missing_fields = extraction_attempt
+                    .as_ref()
+                    .map(Extraction::missing_fields)
+                    .unwrap_or_default();
+                let question = match &extraction_attempt {
+                    Some(_) => params::clarification_question(&intent, &missing_fields),
+                    // Nothing usable came back, so the next answer gets a full extraction.
+                    None => String::from(
+                        "I couldn't quite pick out the details there. Could you describe it again?",
+                    ),
+                };
+                Ok(Turn::new(
+                    Reply::MissingParams {
+                        intent: intent.clone(),
+                        question,
+                        missing_fields,
+                    },
+                    InteractionSteps::ValidateParams {
+                        input_log: vec![text],
+                        intent,
+                        params: extraction_attempt,
+                    },
+                ))
+            }
+            Err(InputParseErr::OtherErr(e)) => Err(e),
+        }
+    }
+
+    async fn validate_params(
+        &self,
+        conversation: &K,
+        (input_log, intent, params): (Vec<String>, Intent, Option<Extraction>),
+        text: String,
+        user: String,
+    ) -> anyhow::Result<Turn> {
+        let additional_params = match &params {
+            Some(existing) => {
+                params::extract_missing(
+                    &self.params_llm,
+                    &intent,
+                    &existing.missing_fields(),
+                    &input_log,
+                    &text,
+                    self.timezone,
+                )
+                .await
+            }
+            None => {
+                params::extract(
+                    &self.params_llm,
+                    &intent,
+                    &[input_log.join("\n"), text.clone()].join("\n"),
+                    self.timezone,
+                )
+                .await
+            }
+        };
+        let latest_params = match additional_params.and_then(|additional| match params.clone() {
+            Some(existing) => existing.merge(additional),
+            None => Ok(additional),
+        }) {
+            Ok(latest_params) => latest_params,
+            Err(e) => {
+                let question = format!(
+                    "I ran into an error extracting the details ({}). Could you try rephrasing?",
+                    e
+                );
+                let missing_fields = params
+                    .as_ref()
+                    .map(Extraction::missing_fields)
+                    .unwrap_or_default();
+                return Ok(Turn::new(
+                    Reply::MissingParams {
+                        intent: intent.clone(),
+                        question,
+                        missing_fields,
+                    },
+                    InteractionSteps::ValidateParams {
+                        input_log,
+                        intent,
+                        params,
+                    },
+                ));
+            }
+        };
+        let mut updated_input_log = input_log;
+        updated_input_log.push(text);
+        self.ask_or_dispatch(conversation, updated_input_log, user, intent, latest_params)
+            .await
+    }
+
+    /// Dispatches if every required param is present; otherwise asks a targeted follow-up
+    /// question for what's missing.
+    async fn ask_or_dispatch(
+        &self,
+        conversation: &K,
+        input_log: Vec<String>,
+        user: String,
+        intent: Intent,
+        params: Extraction,
+    ) -> anyhow::Result<Turn> {
+        let missing_fields = params.missing_fields();
+        if missing_fields.is_empty() {
+            let requester = Requester {
+                user,
+                source_text: input_log.join("\n"),
+            };
+            return self.dispatch(conversation, requester, intent, params).await;
+        }
+        Ok(Turn::new(
+            Reply::MissingParams {
+                intent: intent.clone(),
+                question: params::clarification_question(&intent, &missing_fields),
+                missing_fields,
+            },
+            InteractionSteps::ValidateParams {
+                input_log,
+                intent,
+                params: Some(params),
+            },
+        ))
+    }
+
+    /// Executes straight away, or asks for confirmation first if the policy covers the intent.
+    async fn dispatch(
+        &self,
+        conversation: &K,
+        requester: Requester,
+        intent: Intent,
+        params: Extraction,
+    ) -> anyhow::Result<Turn> {
+        if !self.confirmation_policy.requires_confirmation(&intent) {
+            return self.execute(conversation, requester, intent, params).await;
+        }
+        match execution::prepare(intent, params, &self.task_data_flows).await {
+            Ok(pending) => {
+                let nonce = Uuid::new_v4();
+                Ok(Turn::new(
+                    Reply::Confirm {
+                        intent: pending.intent.clone(),
+                        question: pending.preview(self.timezone),
+                        nonce,
+                    },
+                    InteractionSteps::AwaitConfirmation {
+                        intent: pending.intent,
+                        params: pending.params,
+                        requester,
+                        nonce,
+                    },
+                ))
+            }
+            Err(e) => execution_err(requester, e),
+        }
+    }
+}
+
+fn not_pending() -> Reply {
+    Reply::Failed {
+        message: String::from("There's nothing waiting for confirmation."),
+    }
+}
+
+/// Turns the failures a user can do something about into follow-ups; the rest are errors.
+fn execution_err(requester: Requester, e: ExecutionErr) -> anyhow::Result<Turn> {
+    match e {
+        ExecutionErr::AmbiguousTaskReference {
+            intent,
+            params,
+            candidates,
+        } => Ok(Turn::new(
+            Reply::ChooseTask {
+                intent: intent.clone(),
+                question: String::from(
+                    "I found a few tasks that could match. Which one did you mean? Reply with its number.",
+                ),
+                candidates: candidates.clone(),
+            },
+            InteractionSteps::ChooseTask {
+                intent,
+                params,
+                candidates,
+                requester,
+            },
+        )),
+        ExecutionErr::UndoConflict { current } => Ok(Turn::new(
+            Reply::Failed {
+                message: format!(
+                    "That task has been changed since, so I've left it as it is now:\n{}",
+                    current
+                ),
+            },
+            InteractionSteps::ReceiveInput,
+        )),
+        ExecutionErr::NothingToUndo => Ok(Turn::new(
+            Reply::Failed {
+                message: String::from("There's nothing left to undo."),
+            },
+            InteractionSteps::ReceiveInput,
+        )),
+        ExecutionErr::NoMatchingTask { .. } => Ok(Turn::new(
+            Reply::Failed {
+                message: String::from(
+                    "I couldn't find a task matching that description. Could you describe it differently?",
+                ),
+            },
+            InteractionSteps::ReceiveInput,
+        )),
+        e => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::task::memory;
+    use crate::domain::task::event::{ChangeOrigin, TaskEventQuery};
+    use crate::domain::task::filter::TaskQuery;
+    use crate::domain::task::model::PartialTask;
+    use crate::domain::task::service::Service;
+    use crate::execution::undo::UndoHistory;
+    use crate::llm::backend::scripted as scripted_llm;
+    use crate::transcription::backend::scripted as scripted_transcription;
+    use chrono::{TimeZone, Utc};
+
+    type TestEngine = Engine<
+        scripted_transcription::Client,
+        scripted_llm::Client,
+        Service<memory::Repository>,
+        u32,
+    >;
+
+    const CONVERSATION: u32 = 1;
+
+    fn engine(
+        transcription_client: scripted_transcription::Client,
+        intent_llm: scripted_llm::Client,
+        params_llm: scripted_llm::Client,
+    ) -> TestEngine {
+        Engine {
+            transcription_client,
+            intent_llm,
+            params_llm,
+            reply_llm: scripted_llm::Client::sequence(Vec::<String>::new()),
+            task_data_flows: Service::new(memory::Repository::new()),
+            confirmation_policy: ConfirmationPolicy::default(),
+            reply_renderer: Renderer::Template,
+            timezone: Tz::UTC,
+            undo_history: UndoHistory::new(10),
+        }
+    }
+
+    /// An engine that hears every message as a request to delete the task `params` describes.
+    fn deleting(params: &str) -> TestEngine {
+        engine(
+            scripted_transcription::Client::sequence(Vec::<String>::new()),
+            scripted_llm::Client::sequence([r#"{"intent": "delete task"}"#]),
+            scripted_llm::Client::sequence([params]),
+        )
+    }
+
+    async fn stored_tasks(engine: &TestEngine) -> Vec<Task> {
+        engine
+            .task_data_flows
+            .retrieve_tasks(TaskQuery::default())
+            .await
+            .unwrap()
+    }
+
+    async fn seed(engine: &TestEngine, description: &str, due_day: u32) -> Task {
+        engine
+            .task_data_flows
+            .create_new_task(
+                PartialTask {
+                    description: Some(description.to_string()),
+                    due_date: Some(Utc.with_ymd_and_hms(2026, 10, due_day, 18, 0, 0).unwrap()),
+                    assignee: Some(String::from("Sam")),
+                    ..Default::default()
+                },
+                &ChangeOrigin {
+                    actor: String::from("sam"),
+                    source_text: String::new(),
+                    intent: String::from("CreateNewTask"),
+                    params: serde_json::Value::Null,
+                },
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn say(engine: &TestEngine, step: InteractionSteps, user: &str, text: &str) -> Turn {
+        engine
+            .handle(
+                &CONVERSATION,
+                step,
+                user.to_string(),
+                Inbound::Text(text.to_string()),
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn press(
+        engine: &TestEngine,
+        step: InteractionSteps,
+        user: &str,
+        button: Button,
+        nonce: Uuid,
+    ) -> Turn {
+        engine
+            .handle(
+                &CONVERSATION,
+                step,
+                user.to_string(),
+                Inbound::Button { button, nonce },
+            )
+            .await
+            .unwrap()
+    }
+
+    fn descriptions(tasks: &[Task]) -> Vec<&str> {
+        tasks.iter().map(|task| task.description.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn voice_note_is_transcribed_and_creates_a_task() {
+        let transcript = "Remind Sam to take the bins out on Monday at 6pm";
+        let engine = engine(
+            scripted_transcription::Client::keyed([(vec![1, 2, 3], transcript)]),
+            scripted_llm::Client::sequence([r#"{"intent": "create new task"}"#]),
+            scripted_llm::Client::sequence([r#"{
+                "description": "Take the bins out",
+                "due_date": "2026-10-19T18:00:00+00:00",
+                "assignee": "Sam"
+            }"#]),
+        );
+
+        let turn = engine
+            .handle(
+                &CONVERSATION,
+                InteractionSteps::ReceiveInput,
+                String::from("alex"),
+                Inbound::Audio(vec![1, 2, 3]),
+            )
+            .await
+            .unwrap();
+
+        assert!(matches!(turn.reply, Reply::Done { .. }));
+        assert!(matches!(turn.next, InteractionSteps::ReceiveInput));
+        assert_eq!(turn.transcript.as_deref(), Some(transcript));
+        assert_eq!(
+            engine.transcription_client.recorded_audio(),
+            vec![vec![1, 2, 3]]
+        );
+        assert_eq!(engine.intent_llm.recorded_prompts()[0].prompt, transcript);
+        assert_eq!(engine.params_llm.recorded_prompts()[0].prompt, transcript);
+        let tasks = stored_tasks(&engine).await;
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].description, "Take the bins out");
+        assert_eq!(tasks[0].assignee, "Sam");
+    }
+
+    #[tokio::test]
+    async fn missing_params_are_asked_for_and_merged() {
+        let engine = engine(
+            scripted_transcription::Client::sequence(Vec::<String>::new()),
+            scripted_llm::Client::keyed([("plants", r#"{"intent": "create new task"}"#)]),
+            scripted_llm::Client::sequence([
+                r#"{"description": "Water the plants"}"#,
+                r#"{"due_date": "2026-10-23T17:00:00+00:00", "assignee": "Alex"}"#,
+            ]),
+        );
+
+        let turn = engine
+            .handle(
+                &CONVERSATION,
+                InteractionSteps::ReceiveInput,
+                String::from("sam"),
+                Inbound::Text(String::from("Add a task to water the plants")),
+            )
+            .await
+            .unwrap();
+        let Reply::MissingParams { missing_fields, .. } = &turn.reply else {
+            panic!("expected a follow-up question");
+        };
+        assert_eq!(missing_fields, &vec!["due_date", "assignee"]);
+        assert!(stored_tasks(&engine).await.is_empty());
+
+        let turn = engine
+            .handle(
+                &CONVERSATION,
+                turn.next,
+                String::from("sam"),
+                Inbound::Text(String::from("Alex, by Friday 5pm")),
+            )
+            .await
+            .unwrap();
+
+        assert!(matches!(turn.reply, Reply::Done { .. }));
+        let follow_up = &engine.params_llm.recorded_prompts()[1];
+        assert!(follow_up
+            .prompt
+            .starts_with("Earlier messages:\nAdd a task to water the plants"));
+        let tasks = stored_tasks(&engine).await;
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].description, "Water the plants");
+        assert_eq!(tasks[0].assignee, "Alex");
+        // The intent is only identified once; the answer goes straight to param extraction.
+        assert_eq!(engine.intent_llm.recorded_prompts().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn deletion_waits_for_confirmation_from_whoever_answers() {
+        let engine = deleting(r#"{"description": "bins"}"#);
+        let task = seed(&engine, "Take the bins out", 5).await;
+
+        let turn = say(
+            &engine,
+            InteractionSteps::ReceiveInput,
+            "sam",
+            "Delete the bins task",
+        )
+        .await;
+        let Reply::Confirm { nonce, .. } = turn.reply else {
+            panic!("expected a confirmation request");
+        };
+        assert!(matches!(
+            turn.next,
+            InteractionSteps::AwaitConfirmation { nonce: pending, .. } if pending == nonce
+        ));
+        assert_eq!(stored_tasks(&engine).await.len(), 1);
+
+        let turn = press(&engine, turn.next, "alex", Button::Confirm, nonce).await;
+
+        assert!(matches!(turn.reply, Reply::Done { .. }));
+        assert!(matches!(turn.next, InteractionSteps::ReceiveInput));
+        assert!(stored_tasks(&engine).await.is_empty());
+        let events = engine
+            .task_data_flows
+            .retrieve_events(TaskEventQuery {
+                task_id: Some(task.id),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(events.last().unwrap().actor, "alex");
+    }
+
+    #[tokio::test]
+    async fn previews_show_due_dates_in_the_engine_timezone() {
+        let mut engine = deleting(r#"{"description": "bins"}"#);
+        engine.timezone = chrono_tz::Europe::London;
+        seed(&engine, "Take the bins out", 5).await;
+
+        let turn = say(
+            &engine,
+            InteractionSteps::ReceiveInput,
+            "sam",
+            "Delete the bins task",
+        )
+        .await;
+
+        let Reply::Confirm { question, .. } = turn.reply else {
+            panic!("expected a confirmation request");
+        };
+        assert!(
+            question.contains("Due Date: Mon 05 Oct 19:00"),
+            "{}",
+            question
+        );
+    }
+
+    #[tokio::test]
+    async fn cancelling_a_confirmation_leaves_the_task() {
+        let engine = deleting(r#"{"description": "bins"}"#);
+        seed(&engine, "Take the bins out", 5).await;
+        let turn = say(
+            &engine,
+            InteractionSteps::ReceiveInput,
+            "sam",
+            "Delete the bins task",
+        )
+        .await;
+
+        let Reply::Confirm { nonce, .. } = turn.reply else {
+            panic!("deleting should ask for confirmation");
+        };
+
+        let turn = press(&engine, turn.next, "sam", Button::Cancel, nonce).await;
+
+        assert!(matches!(turn.reply, Reply::Cancelled { .. }));
+        assert!(matches!(turn.next, InteractionSteps::ReceiveInput));
+        assert_eq!(stored_tasks(&engine).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn answers_to_another_confirmation_are_refused() {
+        let engine = deleting(r#"{"description": "bins"}"#);
+        seed(&engine, "Take the bins out", 5).await;
+        let turn = say(
+            &engine,
+            InteractionSteps::ReceiveInput,
+            "sam",
+            "Delete the bins task",
+        )
+        .await;
+        let Reply::Confirm { nonce, .. } = turn.reply else {
+            panic!("expected a confirmation request");
+        };
+
+        let turn = press(&engine, turn.next, "sam", Button::Confirm, Uuid::new_v4()).await;
+
+        assert!(matches!(turn.reply, Reply::Failed { .. }));
+        assert!(matches!(
+            turn.next,
+            InteractionSteps::AwaitConfirmation { nonce: pending, .. } if pending == nonce
+        ));
+        assert_eq!(stored_tasks(&engine).await.len(), 1);
+
+        // Nor is a button press with nothing pending.
+        let turn = press(
+            &engine,
+            InteractionSteps::ReceiveInput,
+            "sam",
+            Button::Confirm,
+            nonce,
+        )
+        .await;
+        assert!(matches!(turn.reply, Reply::Failed { .. }));
+        assert_eq!(stored_tasks(&engine).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn ambiguous_references_are_chosen_by_number() {
+        let engine = deleting(r#"{"description": "take out"}"#);
+        seed(&engine, "Take the bins out", 5).await;
+        seed(&engine, "Take the recycling out", 6).await;
+
+        let turn = say(
+            &engine,
+            InteractionSteps::ReceiveInput,
+            "sam",
+            "Delete take out",
+        )
+        .await;
+        let Reply::ChooseTask { candidates, .. } = &turn.reply else {
+            panic!("expected a choice between tasks");
+        };
+        assert_eq!(
+            descriptions(candidates),
+            vec!["Take the bins out", "Take the recycling out"]
+        );
+
+        let turn = say(&engine, turn.next, "sam", "7").await;
+        assert!(matches!(turn.reply, Reply::ChooseTask { .. }));
+        assert!(matches!(turn.next, InteractionSteps::ChooseTask { .. }));
+
+        let turn = say(&engine, turn.next, "sam", "2.").await;
+        assert!(matches!(turn.reply, Reply::Confirm { .. }));
+        let turn = say(&engine, turn.next, "sam", "yes").await;
+
+        assert!(matches!(turn.reply, Reply::Done { .. }));
+        assert_eq!(
+            descriptions(&stored_tasks(&engine).await),
+            vec!["Take the bins out"]
+        );
+    }
+
+    #[tokio::test]
+    async fn unclassifiable_messages_are_not_understood() {
+        let engine = engine(
+            scripted_transcription::Client::sequence(Vec::<String>::new()),
+            scripted_llm::Client::sequence([r#"{"intent": "no apparent intent"}"#]),
+            scripted_llm::Client::sequence(Vec::<String>::new()),
+        );
+
+        let turn = say(
+            &engine,
+            InteractionSteps::ReceiveInput,
+            "sam",
+            "Lovely weather",
+        )
+        .await;
+
+        assert!(matches!(turn.reply, Reply::NotUnderstood { .. }));
+        assert!(matches!(turn.next, InteractionSteps::ReceiveInput));
+        assert!(engine.params_llm.recorded_prompts().is_empty());
+    }
+
+    #[tokio::test]
+    async fn cancel_drops_a_half_specified_request() {
+        let engine = engine(
+            scripted_transcription::Client::sequence(Vec::<String>::new()),
+            scripted_llm::Client::sequence([r#"{"intent": "create new task"}"#]),
+            scripted_llm::Client::sequence([r#"{"description": "Water the plants"}"#]),
+        );
+        let turn = say(
+            &engine,
+            InteractionSteps::ReceiveInput,
+            "sam",
+            "Add a task to water the plants",
+        )
+        .await;
+        assert!(matches!(turn.next, InteractionSteps::ValidateParams { .. }));
+
+        let turn = say(&engine, turn.next, "sam", "Cancel").await;
+
+        assert!(matches!(turn.reply, Reply::Cancelled { .. }));
+        assert!(matches!(turn.next, InteractionSteps::ReceiveInput));
+        assert!(stored_tasks(&engine).await.is_empty());
+        assert_eq!(engine.params_llm.recorded_prompts().len(), 1);
+    }
+}
@@ -0,0 +1,263 @@
+use super::Database;
+use chrono::{DateTime, Duration, Utc};
+use futures::future::BoxFuture;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+use teloxide::dispatching::dialogue;
+use teloxide::types::ChatId;
+use tokio::time::MissedTickBehavior;
+
+/// How often expired dialogues are deleted. Reads ignore them in the meantime.
+const SWEEP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Durable teloxide dialogue storage, kept in the same database as the tasks so half-finished
+/// conversations survive restarts and deploys. States are stored as JSON; a dialogue untouched
+/// for longer than the TTL is treated as abandoned, and deleted by `sweep_periodically`.
+pub struct Storage {
+    database: Database,
+    ttl: Duration,
+    /// Used only for `Database::Memory`, where there is nothing durable to write to.
+    in_memory: Mutex<HashMap<ChatId, (String, DateTime<Utc>)>>,
+}
+
+#[derive(Debug)]
+pub enum StorageErr {
+    Database(sqlx::Error),
+    Serde(serde_json::Error),
+    /// Returned by `remove_dialogue` when there is no dialogue to remove.
+    DialogueNotFound,
+}
+impl Display for StorageErr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageErr::Database(e) => write!(f, "dialogue storage database error: {}", e),
+            StorageErr::Serde(e) => write!(f, "dialogue (de)serialization error: {}", e),
+            StorageErr::DialogueNotFound => write!(f, "dialogue not found"),
+        }
+    }
+}
+impl std::error::Error for StorageErr {}
+impl From<sqlx::Error> for StorageErr {
+    fn from(value: sqlx::Error) -> Self {
+        StorageErr::Database(value)
+    }
+}
+impl From<serde_json::Error> for StorageErr {
+    fn from(value: serde_json::Error) -> Self {
+        StorageErr::Serde(value)
+    }
+}
+
+impl Storage {
+    pub fn new(database: Database, ttl: Duration) -> Arc<Self> {
+        Arc::new(Self {
+            database,
+            ttl,
+            in_memory: Mutex::new(HashMap::new()),
+        })
+    }
+
+    async fn remove(&self, chat_id: ChatId) -> Result<(), StorageErr> {
+        let removed = match &self.database {
+            Database::Postgres(pool) => {
+                sqlx::query("DELETE FROM dialogues WHERE chat_id = $1")
+                    .bind(chat_id.0)
+                    .execute(pool)
+                    .await?
+                    .rows_affected()
+                    > 0
+            }
+            #[cfg(feature = "sqlite")]
+            Database::Sqlite(pool) => {
+                sqlx::query("DELETE FROM dialogues WHERE chat_id = ?")
+                    .bind(chat_id.0)
+                    .execute(pool)
+                    .await?
+                    .rows_affected()
+                    > 0
+            }
+            Database::Memory => {
+                let mut dialogues = self.in_memory.lock().expect("dialogue lock poisoned");
+                dialogues.remove(&chat_id).is_some()
+            }
+        };
+        if removed {
+            Ok(())
+        } else {
+            Err(StorageErr::DialogueNotFound)
+        }
+    }
+
+    async fn update(&self, chat_id: ChatId, state: String) -> Result<(), StorageErr> {
+        let now = Utc::now();
+        match &self.database {
+            Database::Postgres(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO dialogues (chat_id, state, updated_at)
+                    VALUES ($1, $2::jsonb, $3)
+                    ON CONFLICT (chat_id) DO UPDATE SET
+                        state = EXCLUDED.state,
+                        updated_at = EXCLUDED.updated_at
+                    "#,
+                )
+                .bind(chat_id.0)
+                .bind(state)
+                .bind(now)
+                .execute(pool)
+                .await?;
+            }
+            #[cfg(feature = "sqlite")]
+            Database::Sqlite(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO dialogues (chat_id, state, updated_at)
+                    VALUES (?, ?, ?)
+                    ON CONFLICT (chat_id) DO UPDATE SET
+                        state = excluded.state,
+                        updated_at = excluded.updated_at
+                    "#,
+                )
+                .bind(chat_id.0)
+                .bind(state)
+                .bind(now)
+                .execute(pool)
+                .await?;
+            }
+            Database::Memory => {
+                self.in_memory
+                    .lock()
+                    .expect("dialogue lock poisoned")
+                    .insert(chat_id, (state, now));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the stored state if it is still fresh.
+    async fn get(&self, chat_id: ChatId) -> Result<Option<String>, StorageErr> {
+        let cutoff = Utc::now() - self.ttl;
+        let stored: Option<(String, DateTime<Utc>)> = match &self.database {
+            Database::Postgres(pool) => {
+                sqlx::query_as("SELECT state::text, updated_at FROM dialogues WHERE chat_id = $1")
+                    .bind(chat_id.0)
+                    .fetch_optional(pool)
+                    .await?
+            }
+            #[cfg(feature = "sqlite")]
+            Database::Sqlite(pool) => {
+                sqlx::query_as("SELECT state, updated_at FROM dialogues WHERE chat_id = ?")
+                    .bind(chat_id.0)
+                    .fetch_optional(pool)
+                    .await?
+            }
+            Database::Memory => self
+                .in_memory
+                .lock()
+                .expect("dialogue lock poisoned")
+                .get(&chat_id)
+                .cloned(),
+        };
+        Ok(stored
+            .filter(|(_, updated_at)| *updated_at >= cutoff)
+            .map(|(state, _)| state))
+    }
+
+    /// Deletes every dialogue older than the TTL, returning how many there were.
+    pub async fn sweep(&self) -> Result<u64, StorageErr> {
+        let cutoff = Utc::now() - self.ttl;
+        Ok(match &self.database {
+            Database::Postgres(pool) => sqlx::query("DELETE FROM dialogues WHERE updated_at < $1")
+                .bind(cutoff)
+                .execute(pool)
+                .await?
+                .rows_affected(),
+            #[cfg(feature = "sqlite")]
+            Database::Sqlite(pool) => {
+                sqlx::query("DELETE FROM dialogues WHERE julianday(updated_at) < julianday(?)")
+                    .bind(cutoff)
+                    .execute(pool)
+                    .await?
+                    .rows_affected()
+            }
+            Database::Memory => {
+                let mut dialogues = self.in_memory.lock().expect("dialogue lock poisoned");
+                let before = dialogues.len();
+                dialogues.retain(|_, (_, updated_at)| *updated_at >= cutoff);
+                (before - dialogues.len()) as u64
+            }
+        })
+    }
+
+    /// Sweeps at startup and then every `SWEEP_INTERVAL`, until the process exits. A failed
+    /// sweep is logged and retried on the next tick.
+    pub async fn sweep_periodically(self: Arc<Self>) {
+        let mut ticks = tokio::time::interval(SWEEP_INTERVAL);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticks.tick().await;
+            match self.sweep().await {
+                Ok(0) => {}
+                Ok(swept) => log::info!("Dropped {} expired dialogues", swept),
+                Err(e) => log::error!("Sweeping expired dialogues failed: {}", e),
+            }
+        }
+    }
+}
+
+impl<D> dialogue::Storage<D> for Storage
+where
+    D: Serialize + DeserializeOwned + Send + 'static,
+{
+    type Error = StorageErr;
+
+    fn remove_dialogue(
+        self: Arc<Self>,
+        chat_id: ChatId,
+    ) -> BoxFuture<'static, Result<(), Self::Error>>
+    where
+        D: Send + 'static,
+    {
+        Box::pin(async move { self.remove(chat_id).await })
+    }
+
+    fn update_dialogue(
+        self: Arc<Self>,
+        chat_id: ChatId,
+        dialogue: D,
+    ) -> BoxFuture<'static, Result<(), Self::Error>>
+    where
+        D: Send + 'static,
+    {
+        Box::pin(async move {
+            let state = serde_json::to_string(&dialogue)?;
+            self.update(chat_id, state).await
+        })
+    }
+
+    fn get_dialogue(
+        self: Arc<Self>,
+        chat_id: ChatId,
+    ) -> BoxFuture<'static, Result<Option<D>, Self::Error>> {
+        Box::pin(async move {
+            let Some(state) = self.get(chat_id).await? else {
+                return Ok(None);
+            };
+            match serde_json::from_str(&state) {
+                Ok(dialogue) => Ok(Some(dialogue)),
+                // Usually a state saved by an older version. Starting over beats failing every
+                // message from this chat until the dialogue expires.
+                Err(e) => {
+                    log::warn!("Dropping unreadable dialogue for chat {}: {}", chat_id, e);
+                    match self.remove(chat_id).await {
+                        Ok(()) | Err(StorageErr::DialogueNotFound) => Ok(None),
+                        Err(e) => Err(e),
+                    }
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,309 @@
+use super::Database;
+use chrono::{NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use teloxide::types::ChatId;
+
+const TIME_FORMAT: &str = "%H:%M";
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const SELECT_SCHEDULES: &str =
+    "SELECT chat_id, kind, send_time, timezone, last_sent_on FROM digest_schedules";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DigestKind {
+    /// Each morning: the chat's linked assignees' tasks due today, plus anything overdue.
+    Daily,
+    /// Each Sunday: what was completed over the past week and what slipped.
+    Weekly,
+}
+impl DigestKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DigestKind::Daily => "daily",
+            DigestKind::Weekly => "weekly",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [DigestKind::Daily, DigestKind::Weekly]
+            .into_iter()
+            .find(|kind| kind.as_str() == name)
+    }
+}
+
+/// When one chat gets one kind of digest.
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    pub chat_id: ChatId,
+    pub kind: DigestKind,
+    /// Wall-clock time in `timezone`.
+    pub send_time: NaiveTime,
+    pub timezone: Tz,
+    /// Local date of the last digest sent.
+    pub last_sent_on: Option<NaiveDate>,
+}
+
+type ScheduleRow = (i64, String, String, String, Option<String>);
+
+impl Schedule {
+    fn from_row(
+        (chat_id, kind, send_time, timezone, last_sent_on): ScheduleRow,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            chat_id: ChatId(chat_id),
+            kind: DigestKind::from_name(&kind)
+                .ok_or_else(|| anyhow::anyhow!("unknown digest kind `{}`", kind))?,
+            send_time: NaiveTime::parse_from_str(&send_time, TIME_FORMAT)?,
+            timezone: timezone
+                .parse()
+                .map_err(|e| anyhow::anyhow!("invalid digest timezone: {}", e))?,
+            last_sent_on: last_sent_on
+                .map(|date| NaiveDate::parse_from_str(&date, DATE_FORMAT))
+                .transpose()?,
+        })
+    }
+}
+
+/// Per-chat digest schedules, kept in the same database as the tasks.
+pub struct Store {
+    database: Database,
+    /// Used only for `Database::Memory`, where there is nothing durable to write to.
+    in_memory: Mutex<HashMap<(ChatId, DigestKind), Schedule>>,
+}
+
+impl Store {
+    pub fn new(database: Database) -> Self {
+        Self {
+            database,
+            in_memory: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Creates or reschedules the chat's digest of this kind.
+    pub async fn set(
+        &self,
+        chat_id: ChatId,
+        kind: DigestKind,
+        send_time: NaiveTime,
+        timezone: Tz,
+    ) -> anyhow::Result<()> {
+        self.insert(chat_id, kind, send_time, timezone, true).await
+    }
+
+    /// Creates the chat's digest of this kind unless it already has one, which is left as is.
+    pub async fn set_default(
+        &self,
+        chat_id: ChatId,
+        kind: DigestKind,
+        send_time: NaiveTime,
+        timezone: Tz,
+    ) -> anyhow::Result<()> {
+        self.insert(chat_id, kind, send_time, timezone, false).await
+    }
+
+    async fn insert(
+        &self,
+        chat_id: ChatId,
+        kind: DigestKind,
+        send_time: NaiveTime,
+        timezone: Tz,
+        replace: bool,
+    ) -> anyhow::Result<()> {
+        // A schedule created after today's send time starts tomorrow.
+        let local_now = Utc::now().with_timezone(&timezone);
+        let last_sent_on = (local_now.time() >= send_time).then(|| local_now.date_naive());
+        let on_conflict = if replace {
+            "DO UPDATE SET send_time = excluded.send_time, timezone = excluded.timezone"
+        } else {
+            "DO NOTHING"
+        };
+        match &self.database {
+            Database::Postgres(pool) => {
+                sqlx::query(&format!(
+                    r#"
+                    INSERT INTO digest_schedules (chat_id, kind, send_time, timezone, last_sent_on)
+                    VALUES ($1, $2, $3, $4, $5)
+                    ON CONFLICT (chat_id, kind) {}
+                    "#,
+                    on_conflict
+                ))
+                .bind(chat_id.0)
+                .bind(kind.as_str())
+                .bind(send_time.format(TIME_FORMAT).to_string())
+                .bind(timezone.name())
+                .bind(last_sent_on.map(|date| date.format(DATE_FORMAT).to_string()))
+                .execute(pool)
+                .await?;
+            }
+            #[cfg(feature = "sqlite")]
+            Database::Sqlite(pool) => {
+                sqlx::query(&format!(
+                    r#"
+                    INSERT INTO digest_schedules (chat_id, kind, send_time, timezone, last_sent_on)
+                    VALUES (?, ?, ?, ?, ?)
+                    ON CONFLICT (chat_id, kind) {}
+                    "#,
+                    on_conflict
+                ))
+                .bind(chat_id.0)
+                .bind(kind.as_str())
+                .bind(send_time.format(TIME_FORMAT).to_string())
+                .bind(timezone.name())
+                .bind(last_sent_on.map(|date| date.format(DATE_FORMAT).to_string()))
+                .execute(pool)
+                .await?;
+            }
+            Database::Memory => {
+                let mut schedules = self
+                    .in_memory
+                    .lock()
+                    .expect("digest schedules lock poisoned");
+                let last_sent_on = match schedules.get(&(chat_id, kind)) {
+                    Some(_) if !replace => return Ok(()),
+                    Some(existing) => existing.last_sent_on,
+                    None => last_sent_on,
+                };
+                schedules.insert(
+                    (chat_id, kind),
+                    Schedule {
+                        chat_id,
+                        kind,
+                        send_time,
+                        timezone,
+                        last_sent_on,
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns false if the chat had no digest of this kind.
+    pub async fn remove(&self, chat_id: ChatId, kind: DigestKind) -> anyhow::Result<bool> {
+        Ok(match &self.database {
+            Database::Postgres(pool) => {
+                sqlx::query("DELETE FROM digest_schedules WHERE chat_id = $1 AND kind = $2")
+                    .bind(chat_id.0)
+                    .bind(kind.as_str())
+                    .execute(pool)
+                    .await?
+                    .rows_affected()
+                    > 0
+            }
+            #[cfg(feature = "sqlite")]
+            Database::Sqlite(pool) => {
+                sqlx::query("DELETE FROM digest_schedules WHERE chat_id = ? AND kind = ?")
+                    .bind(chat_id.0)
+                    .bind(kind.as_str())
+                    .execute(pool)
+                    .await?
+                    .rows_affected()
+                    > 0
+            }
+            Database::Memory => self
+                .in_memory
+                .lock()
+                .expect("digest schedules lock poisoned")
+                .remove(&(chat_id, kind))
+                .is_some(),
+        })
+    }
+
+    pub async fn all(&self) -> anyhow::Result<Vec<Schedule>> {
+        let rows = match &self.database {
+            Database::Postgres(pool) => {
+                sqlx::query_as::<_, ScheduleRow>(SELECT_SCHEDULES)
+                    .fetch_all(pool)
+                    .await?
+            }
+            #[cfg(feature = "sqlite")]
+            Database::Sqlite(pool) => {
+                sqlx::query_as::<_, ScheduleRow>(SELECT_SCHEDULES)
+                    .fetch_all(pool)
+                    .await?
+            }
+            Database::Memory => {
+                return Ok(self
+                    .in_memory
+                    .lock()
+                    .expect("digest schedules lock poisoned")
+                    .values()
+                    .cloned()
+                    .collect())
+            }
+        };
+        rows.into_iter().map(Schedule::from_row).collect()
+    }
+
+    /// The chat's schedules; there are few enough in total to filter after loading them all.
+    pub async fn for_chat(&self, chat_id: ChatId) -> anyhow::Result<Vec<Schedule>> {
+        let mut schedules: Vec<Schedule> = self
+            .all()
+            .await?
+            .into_iter()
+            .filter(|schedule| schedule.chat_id == chat_id)
+            .collect();
+        schedules.sort_by_key(|schedule| schedule.kind.as_str());
+        Ok(schedules)
+    }
+
+    /// Records the digest as sent for local date `on`. Returns false if it already was sent that
+    /// day (or later), in which case it must not be sent again.
+    pub async fn mark_sent(
+        &self,
+        chat_id: ChatId,
+        kind: DigestKind,
+        on: NaiveDate,
+    ) -> anyhow::Result<bool> {
+        let on_column = on.format(DATE_FORMAT).to_string();
+        Ok(match &self.database {
+            Database::Postgres(pool) => {
+                sqlx::query(
+                    r#"
+                    UPDATE digest_schedules SET last_sent_on = $3
+                    WHERE chat_id = $1 AND kind = $2
+                      AND (last_sent_on IS NULL OR last_sent_on < $3)
+                    "#,
+                )
+                .bind(chat_id.0)
+                .bind(kind.as_str())
+                .bind(on_column)
+                .execute(pool)
+                .await?
+                .rows_affected()
+                    > 0
+            }
+            #[cfg(feature = "sqlite")]
+            Database::Sqlite(pool) => {
+                sqlx::query(
+                    r#"
+                    UPDATE digest_schedules SET last_sent_on = ?3
+                    WHERE chat_id = ?1 AND kind = ?2
+                      AND (last_sent_on IS NULL OR last_sent_on < ?3)
+                    "#,
+                )
+                .bind(chat_id.0)
+                .bind(kind.as_str())
+                .bind(on_column)
+                .execute(pool)
+                .await?
+                .rows_affected()
+                    > 0
+            }
+            Database::Memory => {
+                let mut schedules = self
+                    .in_memory
+                    .lock()
+                    .expect("digest schedules lock poisoned");
+                match schedules.get_mut(&(chat_id, kind)) {
+                    Some(schedule) if schedule.last_sent_on.map_or(true, |last| last < on) => {
+                        schedule.last_sent_on = Some(on);
+                        true
+                    }
+                    _ => false,
+                }
+            }
+        })
+    }
+}
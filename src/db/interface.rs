@@ -1,10 +1,57 @@
 use async_trait::async_trait;
+use serde::de::StdError;
+use std::fmt::{Display, Formatter};
 use uuid::Uuid;
+
+/// Errors every `Repository` implementation reports the same way, so callers can match on them
+/// regardless of backend.
+#[derive(Debug)]
+pub enum RepositoryErr {
+    /// No live (non-deleted) entity has this id.
+    NotFound { id: Uuid },
+}
+impl Display for RepositoryErr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RepositoryErr::NotFound { id } => write!(f, "No record found with id {}", id),
+        }
+    }
+}
+impl StdError for RepositoryErr {}
+
 #[async_trait]
-// FIXME: Pending system design surrounding llm-determined retrievals
-// Maybe we just resrict retrievals for now along some predefined params?
 pub trait Repository<T> {
+    /// Structured retrieval criteria understood by this repository, compiled by the
+    /// implementation into its own parameterised query language.
+    type Filter;
+
     async fn save(&self, new: T) -> anyhow::Result<T>;
     async fn retrieve_by_id(&self, id: Uuid) -> anyhow::Result<T>;
-    async fn delete_by_id(&self, id: Uuid) -> anyhow::Result<T>;
+    /// Like `retrieve_by_id`, but also finds soft-deleted entities.
+    async fn retrieve_by_id_including_deleted(&self, id: Uuid) -> anyhow::Result<T>;
+    async fn retrieve_by_filter(&self, filter: Self::Filter) -> anyhow::Result<Vec<T>>;
+}
+
+/// Repositories that keep an append-only change log next to their entities.
+#[async_trait]
+pub trait EventLog<T, E>: Repository<T> {
+    /// Retrieval criteria for logged events.
+    type EventFilter;
+
+    /// Saves the entity and appends the event describing the change in one transaction, so the
+    /// log never disagrees with the stored state.
+    async fn save_with_event(&self, new: T, event: E) -> anyhow::Result<T>;
+    /// Reads the entity, lets `change` turn it into the new state and the event recording that,
+    /// and saves both, all in one transaction that keeps concurrent writers off the entity in
+    /// between. Fails with `RepositoryErr::NotFound` if there is no live entity with this id, or
+    /// no entity at all with `include_deleted`.
+    async fn update_with_event<F>(
+        &self,
+        id: Uuid,
+        include_deleted: bool,
+        change: F,
+    ) -> anyhow::Result<T>
+    where
+        F: FnOnce(T) -> anyhow::Result<(T, E)> + Send;
+    async fn retrieve_events(&self, filter: Self::EventFilter) -> anyhow::Result<Vec<E>>;
 }
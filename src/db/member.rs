@@ -0,0 +1,162 @@
+use super::Database;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use teloxide::types::UserId;
+
+/// How a member got access.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ApprovedVia {
+    Passphrase,
+    AllowList,
+}
+impl ApprovedVia {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApprovedVia::Passphrase => "passphrase",
+            ApprovedVia::AllowList => "allow_list",
+        }
+    }
+}
+
+/// Telegram users who have been let in, kept in the same database as the tasks so approvals
+/// survive restarts.
+pub struct Store {
+    database: Database,
+    /// Used only for `Database::Memory`, where there is nothing durable to write to.
+    in_memory: Mutex<HashMap<UserId, ApprovedVia>>,
+}
+
+impl Store {
+    pub fn new(database: Database) -> Self {
+        Self {
+            database,
+            in_memory: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn is_approved(&self, user_id: UserId) -> anyhow::Result<bool> {
+        let user_id_column = i64::try_from(user_id.0)?;
+        Ok(match &self.database {
+            Database::Postgres(pool) => sqlx::query_scalar::<_, i64>(
+                "SELECT user_id FROM approved_members WHERE user_id = $1",
+            )
+            .bind(user_id_column)
+            .fetch_optional(pool)
+            .await?
+            .is_some(),
+            #[cfg(feature = "sqlite")]
+            Database::Sqlite(pool) => sqlx::query_scalar::<_, i64>(
+                "SELECT user_id FROM approved_members WHERE user_id = ?",
+            )
+            .bind(user_id_column)
+            .fetch_optional(pool)
+            .await?
+            .is_some(),
+            Database::Memory => self
+                .in_memory
+                .lock()
+                .expect("approved members lock poisoned")
+                .contains_key(&user_id),
+        })
+    }
+
+    /// Records the approval. Approving an already approved member keeps the original record.
+    pub async fn approve(&self, user_id: UserId, via: ApprovedVia) -> anyhow::Result<()> {
+        let user_id_column = i64::try_from(user_id.0)?;
+        match &self.database {
+            Database::Postgres(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO approved_members (user_id, approved_via, approved_at)
+                    VALUES ($1, $2, $3)
+                    ON CONFLICT (user_id) DO NOTHING
+                    "#,
+                )
+                .bind(user_id_column)
+                .bind(via.as_str())
+                .bind(Utc::now())
+                .execute(pool)
+                .await?;
+            }
+            #[cfg(feature = "sqlite")]
+            Database::Sqlite(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO approved_members (user_id, approved_via, approved_at)
+                    VALUES (?, ?, ?)
+                    ON CONFLICT (user_id) DO NOTHING
+                    "#,
+                )
+                .bind(user_id_column)
+                .bind(via.as_str())
+                .bind(Utc::now())
+                .execute(pool)
+                .await?;
+            }
+            Database::Memory => {
+                self.in_memory
+                    .lock()
+                    .expect("approved members lock poisoned")
+                    .entry(user_id)
+                    .or_insert(via);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the approvals made through the allow-list for everyone not in `keep`. Members who
+    /// unlocked the bot with the passphrase are left alone. Returns how many were revoked.
+    pub async fn revoke_allow_list_except(&self, keep: &[UserId]) -> anyhow::Result<u64> {
+        let keep_columns = keep
+            .iter()
+            .map(|user_id| i64::try_from(user_id.0))
+            .collect::<Result<Vec<i64>, _>>()?;
+        Ok(match &self.database {
+            Database::Postgres(pool) => sqlx::query(
+                r#"
+                    DELETE FROM approved_members
+                    WHERE approved_via = $1 AND NOT (user_id = ANY($2))
+                    "#,
+            )
+            .bind(ApprovedVia::AllowList.as_str())
+            .bind(keep_columns)
+            .execute(pool)
+            .await?
+            .rows_affected(),
+            #[cfg(feature = "sqlite")]
+            Database::Sqlite(pool) => {
+                // SQLite can't bind a list, but there are only ever a handful of these rows.
+                let listed = sqlx::query_scalar::<_, i64>(
+                    "SELECT user_id FROM approved_members WHERE approved_via = ?",
+                )
+                .bind(ApprovedVia::AllowList.as_str())
+                .fetch_all(pool)
+                .await?;
+                let mut revoked = 0;
+                for user_id in listed
+                    .into_iter()
+                    .filter(|user_id| !keep_columns.contains(user_id))
+                {
+                    revoked += sqlx::query("DELETE FROM approved_members WHERE user_id = ?")
+                        .bind(user_id)
+                        .execute(pool)
+                        .await?
+                        .rows_affected();
+                }
+                revoked
+            }
+            Database::Memory => {
+                let mut members = self
+                    .in_memory
+                    .lock()
+                    .expect("approved members lock poisoned");
+                let before = members.len();
+                members.retain(|user_id, via| {
+                    *via != ApprovedVia::AllowList || keep.contains(user_id)
+                });
+                (before - members.len()) as u64
+            }
+        })
+    }
+}
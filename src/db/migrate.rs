@@ -0,0 +1,69 @@
+use super::Database;
+use sqlx::migrate::Migrator;
+
+static POSTGRES_MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres");
+#[cfg(feature = "sqlite")]
+static SQLITE_MIGRATOR: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+/// Applies every embedded migration the database hasn't seen yet. Refuses to touch a database
+/// whose schema is newer than this binary, since running old code against it risks writing rows
+/// the newer schema doesn't expect.
+pub async fn run(database: &Database) -> anyhow::Result<()> {
+    match database {
+        Database::Postgres(pool) => {
+            let applied = sqlx::query_scalar::<_, i64>("SELECT version FROM _sqlx_migrations")
+                .fetch_all(pool)
+                .await;
+            // 42P01 is undefined_table.
+            let applied = empty_if_untracked(applied, |e| e.code().as_deref() == Some("42P01"))?;
+            check_not_ahead(&POSTGRES_MIGRATOR, &applied)?;
+            POSTGRES_MIGRATOR.run(pool).await?;
+        }
+        #[cfg(feature = "sqlite")]
+        Database::Sqlite(pool) => {
+            let applied = sqlx::query_scalar::<_, i64>("SELECT version FROM _sqlx_migrations")
+                .fetch_all(pool)
+                .await;
+            // SQLite reports every missing table under the generic SQLITE_ERROR code.
+            let applied =
+                empty_if_untracked(applied, |e| e.message().starts_with("no such table"))?;
+            check_not_ahead(&SQLITE_MIGRATOR, &applied)?;
+            SQLITE_MIGRATOR.run(pool).await?;
+        }
+        Database::Memory => {}
+    }
+    Ok(())
+}
+
+/// The applied versions, or none if the migrations table doesn't exist yet, as before the first
+/// migration run. Any other failure is returned: treating it as an empty history would skip the
+/// check against a newer schema.
+fn empty_if_untracked(
+    applied: Result<Vec<i64>, sqlx::Error>,
+    is_undefined_table: fn(&dyn sqlx::error::DatabaseError) -> bool,
+) -> anyhow::Result<Vec<i64>> {
+    match applied {
+        Err(sqlx::Error::Database(e)) if is_undefined_table(e.as_ref()) => Ok(Vec::new()),
+        applied => Ok(applied?),
+    }
+}
+
+fn check_not_ahead(migrator: &Migrator, applied: &[i64]) -> anyhow::Result<()> {
+    let latest_known = migrator
+        .iter()
+        .map(|migration| migration.version)
+        .max()
+        .unwrap_or(0);
+    if let Some(unknown) = applied
+        .iter()
+        .filter(|version| !migrator.version_exists(**version))
+        .max()
+    {
+        anyhow::bail!(
+            "Database schema is at migration {}, but this binary only knows migrations up to {}. Upgrade the binary before starting it against this database.",
+            unknown,
+            latest_known
+        );
+    }
+    Ok(())
+}
@@ -1,2 +1,46 @@
+pub mod dialogue;
+pub mod digest;
 pub mod interface;
+pub mod member;
+pub mod migrate;
+pub mod reminder;
 pub mod task;
+
+/// Connection to the backend named by the configured database URL, shared by every repository.
+#[derive(Clone)]
+pub enum Database {
+    Postgres(sqlx::PgPool),
+    #[cfg(feature = "sqlite")]
+    Sqlite(sqlx::SqlitePool),
+    /// Throwaway in-process storage; nothing survives a restart.
+    Memory,
+}
+
+/// Connects to the backend named by the URL scheme: `postgres://` / `postgresql://`, `sqlite:`
+/// (with the `sqlite` feature), or `memory:`.
+pub async fn connect(database_url: &str) -> anyhow::Result<Database> {
+    if database_url.starts_with("postgres://") || database_url.starts_with("postgresql://") {
+        return Ok(Database::Postgres(
+            sqlx::PgPool::connect(database_url).await?,
+        ));
+    }
+    if database_url.starts_with("sqlite:") {
+        #[cfg(feature = "sqlite")]
+        {
+            let options = database_url
+                .parse::<sqlx::sqlite::SqliteConnectOptions>()?
+                .create_if_missing(true);
+            return Ok(Database::Sqlite(
+                sqlx::SqlitePool::connect_with(options).await?,
+            ));
+        }
+        #[cfg(not(feature = "sqlite"))]
+        anyhow::bail!(
+            "DATABASE_URL points at SQLite, but this binary was built without the `sqlite` feature"
+        );
+    }
+    if database_url.starts_with("memory:") {
+        return Ok(Database::Memory);
+    }
+    anyhow::bail!("Unsupported DATABASE_URL scheme: {}", database_url)
+}
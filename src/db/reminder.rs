@@ -0,0 +1,191 @@
+use super::Database;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use teloxide::types::ChatId;
+use uuid::Uuid;
+
+/// Where reminders go and which have gone out, kept in the same database as the tasks so a
+/// restart neither loses the links nor sends a reminder twice.
+pub struct Store {
+    database: Database,
+    /// Used only for `Database::Memory`, where there is nothing durable to write to.
+    in_memory_chats: Mutex<HashMap<String, ChatId>>,
+    in_memory_sent: Mutex<HashSet<(Uuid, i64, DateTime<Utc>)>>,
+}
+
+impl Store {
+    pub fn new(database: Database) -> Self {
+        Self {
+            database,
+            in_memory_chats: Mutex::new(HashMap::new()),
+            in_memory_sent: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Sends `assignee`'s reminders to `chat_id` from now on, replacing any earlier link.
+    pub async fn link(&self, assignee: &str, chat_id: ChatId) -> anyhow::Result<()> {
+        let assignee = assignee.trim().to_lowercase();
+        match &self.database {
+            Database::Postgres(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO assignee_chats (assignee, chat_id, linked_at)
+                    VALUES ($1, $2, $3)
+                    ON CONFLICT (assignee) DO UPDATE
+                    SET chat_id = excluded.chat_id, linked_at = excluded.linked_at
+                    "#,
+                )
+                .bind(assignee)
+                .bind(chat_id.0)
+                .bind(Utc::now())
+                .execute(pool)
+                .await?;
+            }
+            #[cfg(feature = "sqlite")]
+            Database::Sqlite(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO assignee_chats (assignee, chat_id, linked_at)
+                    VALUES (?, ?, ?)
+                    ON CONFLICT (assignee) DO UPDATE
+                    SET chat_id = excluded.chat_id, linked_at = excluded.linked_at
+                    "#,
+                )
+                .bind(assignee)
+                .bind(chat_id.0)
+                .bind(Utc::now())
+                .execute(pool)
+                .await?;
+            }
+            Database::Memory => {
+                self.in_memory_chats
+                    .lock()
+                    .expect("assignee chats lock poisoned")
+                    .insert(assignee, chat_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// The chat linked to `assignee` (case-insensitive), if any.
+    pub async fn linked_chat(&self, assignee: &str) -> anyhow::Result<Option<ChatId>> {
+        let assignee = assignee.trim().to_lowercase();
+        let chat_id = match &self.database {
+            Database::Postgres(pool) => {
+                sqlx::query_scalar::<_, i64>(
+                    "SELECT chat_id FROM assignee_chats WHERE assignee = $1",
+                )
+                .bind(assignee)
+                .fetch_optional(pool)
+                .await?
+            }
+            #[cfg(feature = "sqlite")]
+            Database::Sqlite(pool) => {
+                sqlx::query_scalar::<_, i64>(
+                    "SELECT chat_id FROM assignee_chats WHERE assignee = ?",
+                )
+                .bind(assignee)
+                .fetch_optional(pool)
+                .await?
+            }
+            Database::Memory => {
+                return Ok(self
+                    .in_memory_chats
+                    .lock()
+                    .expect("assignee chats lock poisoned")
+                    .get(&assignee)
+                    .copied())
+            }
+        };
+        Ok(chat_id.map(ChatId))
+    }
+
+    /// The assignees whose reminders go to `chat_id`, lowercased.
+    pub async fn linked_assignees(&self, chat_id: ChatId) -> anyhow::Result<Vec<String>> {
+        Ok(match &self.database {
+            Database::Postgres(pool) => {
+                sqlx::query_scalar::<_, String>(
+                    "SELECT assignee FROM assignee_chats WHERE chat_id = $1 ORDER BY assignee",
+                )
+                .bind(chat_id.0)
+                .fetch_all(pool)
+                .await?
+            }
+            #[cfg(feature = "sqlite")]
+            Database::Sqlite(pool) => {
+                sqlx::query_scalar::<_, String>(
+                    "SELECT assignee FROM assignee_chats WHERE chat_id = ? ORDER BY assignee",
+                )
+                .bind(chat_id.0)
+                .fetch_all(pool)
+                .await?
+            }
+            Database::Memory => {
+                let mut assignees: Vec<String> = self
+                    .in_memory_chats
+                    .lock()
+                    .expect("assignee chats lock poisoned")
+                    .iter()
+                    .filter(|(_, linked)| **linked == chat_id)
+                    .map(|(assignee, _)| assignee.clone())
+                    .collect();
+                assignees.sort();
+                assignees
+            }
+        })
+    }
+
+    /// Records the reminder `offset_minutes` before `due_date` for a task as sent. Returns false
+    /// if it already was, in which case it must not be sent again. Claiming before sending means
+    /// a crash in between loses that reminder rather than repeating it.
+    pub async fn claim(
+        &self,
+        task_id: Uuid,
+        offset_minutes: i64,
+        due_date: DateTime<Utc>,
+    ) -> anyhow::Result<bool> {
+        Ok(match &self.database {
+            Database::Postgres(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO reminders_sent (task_id, offset_minutes, due_date, sent_at)
+                    VALUES ($1, $2, $3, $4)
+                    ON CONFLICT (task_id, offset_minutes, due_date) DO NOTHING
+                    "#,
+                )
+                .bind(task_id)
+                .bind(offset_minutes)
+                .bind(due_date)
+                .bind(Utc::now())
+                .execute(pool)
+                .await?
+                .rows_affected()
+                    > 0
+            }
+            #[cfg(feature = "sqlite")]
+            Database::Sqlite(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO reminders_sent (task_id, offset_minutes, due_date, sent_at)
+                    VALUES (?, ?, ?, ?)
+                    ON CONFLICT (task_id, offset_minutes, due_date) DO NOTHING
+                    "#,
+                )
+                .bind(task_id)
+                .bind(offset_minutes)
+                .bind(due_date)
+                .bind(Utc::now())
+                .execute(pool)
+                .await?
+                .rows_affected()
+                    > 0
+            }
+            Database::Memory => self
+                .in_memory_sent
+                .lock()
+                .expect("sent reminders lock poisoned")
+                .insert((task_id, offset_minutes, due_date)),
+        })
+    }
+}
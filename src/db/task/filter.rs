@@ -0,0 +1,451 @@
+use crate::domain::task::event::TaskEventQuery;
+use crate::domain::task::filter::{SortDirection, SortField, TaskFilter, TaskQuery};
+use chrono::{DateTime, Utc};
+use sqlx::{Encode, QueryBuilder, Type};
+use uuid::Uuid;
+
+/// The per-database differences in how a `TaskQuery` is compiled.
+pub(super) trait Dialect: sqlx::Database {
+    /// Case-insensitive `LIKE` operator.
+    const LIKE: &'static str;
+    /// Wrapped around timestamp columns and parameters so comparisons and ordering are
+    /// chronological rather than textual.
+    const TIMESTAMP_OPEN: &'static str;
+    const TIMESTAMP_CLOSE: &'static str;
+}
+
+impl Dialect for sqlx::Postgres {
+    const LIKE: &'static str = "ILIKE";
+    const TIMESTAMP_OPEN: &'static str = "";
+    const TIMESTAMP_CLOSE: &'static str = "";
+}
+
+/// SQLite stores timestamps as RFC 3339 text with a variable number of fractional digits, so
+/// compare them through `julianday`. Its `LIKE` is already case-insensitive for ASCII.
+#[cfg(feature = "sqlite")]
+impl Dialect for sqlx::Sqlite {
+    const LIKE: &'static str = "LIKE";
+    const TIMESTAMP_OPEN: &'static str = "julianday(";
+    const TIMESTAMP_CLOSE: &'static str = ")";
+}
+
+/// Appends `WHERE`, `ORDER BY` and `LIMIT` clauses for `query` to a `SELECT ... FROM tasks`
+/// statement. Every user-supplied value goes through `push_bind`; only fixed SQL fragments are
+/// pushed as text.
+pub(super) fn push_query<'args, DB>(builder: &mut QueryBuilder<'args, DB>, query: &TaskQuery)
+where
+    DB: Dialect,
+    String: Encode<'args, DB> + Type<DB>,
+    i64: Encode<'args, DB> + Type<DB>,
+    DateTime<Utc>: Encode<'args, DB> + Type<DB>,
+{
+    builder.push(" WHERE ");
+    if query.include_deleted {
+        builder.push("TRUE");
+    } else {
+        builder.push("deleted_at IS NULL");
+    }
+    if let Some(filter) = &query.filter {
+        builder.push(" AND (");
+        push_filter(builder, filter);
+        builder.push(")");
+    }
+
+    builder.push(" ORDER BY ");
+    for sort in &query.sort {
+        push_sort_column::<DB>(builder, sort.field);
+        builder.push(match sort.direction {
+            SortDirection::Ascending => " ASC, ",
+            SortDirection::Descending => " DESC, ",
+        });
+    }
+    // Always finish on a total order so pagination and repeated queries are stable.
+    push_timestamp_column(builder, "due_date");
+    builder.push(" ASC, task_id ASC");
+
+    builder.push(" LIMIT ");
+    builder.push_bind(i64::from(query.effective_limit()));
+}
+
+/// Appends `WHERE`, `ORDER BY` and `LIMIT` clauses for `query` to a `SELECT ... FROM
+/// task_events` statement, oldest event first.
+pub(super) fn push_event_query<'args, DB>(
+    builder: &mut QueryBuilder<'args, DB>,
+    query: &TaskEventQuery,
+) where
+    DB: Dialect,
+    String: Encode<'args, DB> + Type<DB>,
+    i64: Encode<'args, DB> + Type<DB>,
+    Uuid: Encode<'args, DB> + Type<DB>,
+    DateTime<Utc>: Encode<'args, DB> + Type<DB>,
+{
+    builder.push(" WHERE TRUE");
+    if let Some(task_id) = query.task_id {
+        builder.push(" AND task_id = ");
+        builder.push_bind(task_id);
+    }
+    if let Some(actor) = &query.actor {
+        builder.push(" AND actor = ");
+        builder.push_bind(actor.clone());
+    }
+    if let Some(since) = query.since {
+        builder.push(" AND ");
+        push_timestamp_column(builder, "occurred_at");
+        builder.push(" >= ");
+        push_timestamp_bind(builder, since);
+    }
+    if let Some(until) = query.until {
+        builder.push(" AND ");
+        push_timestamp_column(builder, "occurred_at");
+        builder.push(" < ");
+        push_timestamp_bind(builder, until);
+    }
+    builder.push(" ORDER BY ");
+    push_timestamp_column(builder, "occurred_at");
+    builder.push(" ASC, event_id ASC");
+    if let Some(limit) = query.limit {
+        builder.push(" LIMIT ");
+        builder.push_bind(i64::from(limit));
+    }
+}
+
+fn push_filter<'args, DB>(builder: &mut QueryBuilder<'args, DB>, filter: &TaskFilter)
+where
+    DB: Dialect,
+    String: Encode<'args, DB> + Type<DB>,
+    DateTime<Utc>: Encode<'args, DB> + Type<DB>,
+{
+    match filter {
+        TaskFilter::AssigneeEquals { assignee } => {
+            builder.push("LOWER(assignee) = LOWER(");
+            builder.push_bind(assignee.clone());
+            builder.push(")");
+        }
+        TaskFilter::AssigneeIn { assignees } => {
+            if assignees.is_empty() {
+                builder.push("FALSE");
+                return;
+            }
+            builder.push("LOWER(assignee) IN (");
+            let mut separated = builder.separated(", ");
+            for assignee in assignees {
+                separated.push("LOWER(");
+                separated.push_bind_unseparated(assignee.clone());
+                separated.push_unseparated(")");
+            }
+            builder.push(")");
+        }
+        TaskFilter::DueBefore { before } => {
+            push_timestamp_column(builder, "due_date");
+            builder.push(" < ");
+            push_timestamp_bind(builder, *before);
+        }
+        TaskFilter::DueAfter { after } => {
+            push_timestamp_column(builder, "due_date");
+            builder.push(" >= ");
+            push_timestamp_bind(builder, *after);
+        }
+        TaskFilter::DueBetween { start, end } => {
+            builder.push("(");
+            push_timestamp_column(builder, "due_date");
+            builder.push(" >= ");
+            push_timestamp_bind(builder, *start);
+            builder.push(" AND ");
+            push_timestamp_column(builder, "due_date");
+            builder.push(" < ");
+            push_timestamp_bind(builder, *end);
+            builder.push(")");
+        }
+        TaskFilter::CreatedBefore { before } => {
+            push_timestamp_column(builder, "create_date");
+            builder.push(" < ");
+            push_timestamp_bind(builder, *before);
+        }
+        TaskFilter::CreatedAfter { after } => {
+            push_timestamp_column(builder, "create_date");
+            builder.push(" >= ");
+            push_timestamp_bind(builder, *after);
+        }
+        TaskFilter::CreatedBetween { start, end } => {
+            builder.push("(");
+            push_timestamp_column(builder, "create_date");
+            builder.push(" >= ");
+            push_timestamp_bind(builder, *start);
+            builder.push(" AND ");
+            push_timestamp_column(builder, "create_date");
+            builder.push(" < ");
+            push_timestamp_bind(builder, *end);
+            builder.push(")");
+        }
+        TaskFilter::CompletedBetween { start, end } => {
+            builder.push("(");
+            push_timestamp_column(builder, "completed_at");
+            builder.push(" >= ");
+            push_timestamp_bind(builder, *start);
+            builder.push(" AND ");
+            push_timestamp_column(builder, "completed_at");
+            builder.push(" < ");
+            push_timestamp_bind(builder, *end);
+            builder.push(")");
+        }
+        TaskFilter::DescriptionContains { text } => {
+            builder.push("description ");
+            builder.push(DB::LIKE);
+            builder.push(" ");
+            builder.push_bind(format!("%{}%", escape_like(text)));
+            builder.push(" ESCAPE '\\'");
+        }
+        TaskFilter::Status { status } => {
+            builder.push("status = ");
+            builder.push_bind(status.as_str().to_string());
+        }
+        TaskFilter::And { filters } => push_junction(builder, filters, " AND ", "TRUE"),
+        TaskFilter::Or { filters } => push_junction(builder, filters, " OR ", "FALSE"),
+        TaskFilter::Not { filter } => {
+            builder.push("NOT (");
+            push_filter(builder, filter);
+            builder.push(")");
+        }
+    }
+}
+
+fn push_junction<'args, DB>(
+    builder: &mut QueryBuilder<'args, DB>,
+    filters: &[TaskFilter],
+    operator: &str,
+    identity: &str,
+) where
+    DB: Dialect,
+    String: Encode<'args, DB> + Type<DB>,
+    DateTime<Utc>: Encode<'args, DB> + Type<DB>,
+{
+    if filters.is_empty() {
+        builder.push(identity);
+        return;
+    }
+    builder.push("(");
+    for (i, filter) in filters.iter().enumerate() {
+        if i > 0 {
+            builder.push(operator);
+        }
+        builder.push("(");
+        push_filter(builder, filter);
+        builder.push(")");
+    }
+    builder.push(")");
+}
+
+fn push_sort_column<DB: Dialect>(builder: &mut QueryBuilder<'_, DB>, field: SortField) {
+    match field {
+        SortField::DueDate => push_timestamp_column(builder, "due_date"),
+        SortField::CreateDate => push_timestamp_column(builder, "create_date"),
+        SortField::Assignee => {
+            builder.push("LOWER(assignee)");
+        }
+        SortField::Description => {
+            builder.push("LOWER(description)");
+        }
+    }
+}
+
+fn push_timestamp_column<DB: Dialect>(builder: &mut QueryBuilder<'_, DB>, column: &'static str) {
+    builder.push(DB::TIMESTAMP_OPEN);
+    builder.push(column);
+    builder.push(DB::TIMESTAMP_CLOSE);
+}
+
+fn push_timestamp_bind<'args, DB>(builder: &mut QueryBuilder<'args, DB>, value: DateTime<Utc>)
+where
+    DB: Dialect,
+    DateTime<Utc>: Encode<'args, DB> + Type<DB>,
+{
+    builder.push(DB::TIMESTAMP_OPEN);
+    builder.push_bind(value);
+    builder.push(DB::TIMESTAMP_CLOSE);
+}
+
+/// Escapes `LIKE` wildcards so user text is matched literally under `ESCAPE '\'`.
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::task::filter::{TaskSort, MAX_QUERY_LIMIT};
+    use crate::domain::task::model::TaskStatus;
+    use chrono::TimeZone;
+    use sqlx::Postgres;
+
+    const SELECT: &str = "SELECT task_id FROM tasks";
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, day, 12, 0, 0).unwrap()
+    }
+
+    fn postgres_sql(query: &TaskQuery) -> String {
+        let mut builder = QueryBuilder::<Postgres>::new(SELECT);
+        push_query(&mut builder, query);
+        builder.sql().strip_prefix(SELECT).unwrap().to_string()
+    }
+
+    #[test]
+    fn default_query_hides_deleted_tasks_and_binds_the_limit() {
+        assert_eq!(
+            postgres_sql(&TaskQuery::default()),
+            " WHERE deleted_at IS NULL ORDER BY due_date ASC, task_id ASC LIMIT $1"
+        );
+    }
+
+    #[test]
+    fn nested_filters_bind_values_in_order() {
+        let query = TaskQuery {
+            filter: Some(TaskFilter::And {
+                filters: vec![
+                    TaskFilter::AssigneeIn {
+                        assignees: vec![String::from("Sam"), String::from("Alex")],
+                    },
+                    TaskFilter::Or {
+                        filters: vec![
+                            TaskFilter::DescriptionContains {
+                                text: String::from("50%_off"),
+                            },
+                            TaskFilter::Not {
+                                filter: Box::new(TaskFilter::Status {
+                                    status: TaskStatus::Done,
+                                }),
+                            },
+                        ],
+                    },
+                    TaskFilter::DueBetween {
+                        start: at(1),
+                        end: at(8),
+                    },
+                ],
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            postgres_sql(&query),
+            concat!(
+                " WHERE deleted_at IS NULL AND (",
+                "((LOWER(assignee) IN (LOWER($1), LOWER($2)))",
+                r" AND (((description ILIKE $3 ESCAPE '\') OR (NOT (status = $4))))",
+                " AND ((due_date >= $5 AND due_date < $6)))",
+                ") ORDER BY due_date ASC, task_id ASC LIMIT $7"
+            )
+        );
+    }
+
+    #[test]
+    fn empty_junctions_and_lists_compile_to_constants() {
+        let query = TaskQuery {
+            filter: Some(TaskFilter::Or {
+                filters: vec![
+                    TaskFilter::And { filters: vec![] },
+                    TaskFilter::AssigneeIn { assignees: vec![] },
+                ],
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            postgres_sql(&query),
+            " WHERE deleted_at IS NULL AND (((TRUE) OR (FALSE))) ORDER BY due_date ASC, task_id ASC LIMIT $1"
+        );
+    }
+
+    #[test]
+    fn requested_sorts_come_before_the_tie_breakers() {
+        let query = TaskQuery {
+            sort: vec![
+                TaskSort {
+                    field: SortField::Assignee,
+                    direction: SortDirection::Descending,
+                },
+                TaskSort {
+                    field: SortField::Description,
+                    direction: SortDirection::Ascending,
+                },
+            ],
+            include_deleted: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            postgres_sql(&query),
+            " WHERE TRUE ORDER BY LOWER(assignee) DESC, LOWER(description) ASC, due_date ASC, task_id ASC LIMIT $1"
+        );
+    }
+
+    #[test]
+    fn the_limit_is_capped_and_always_the_last_bind() {
+        let query = TaskQuery {
+            filter: Some(TaskFilter::DueBefore { before: at(8) }),
+            limit: Some(MAX_QUERY_LIMIT * 10),
+            ..Default::default()
+        };
+
+        assert_eq!(query.effective_limit(), MAX_QUERY_LIMIT);
+        assert!(postgres_sql(&query)
+            .ends_with("due_date < $1) ORDER BY due_date ASC, task_id ASC LIMIT $2"));
+        let small = TaskQuery {
+            limit: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(small.effective_limit(), 3);
+    }
+
+    #[test]
+    fn event_queries_bind_every_given_criterion() {
+        let mut builder = QueryBuilder::<Postgres>::new("");
+        push_event_query(
+            &mut builder,
+            &TaskEventQuery {
+                task_id: Some(Uuid::new_v4()),
+                actor: Some(String::from("Sam")),
+                since: Some(at(1)),
+                until: Some(at(8)),
+                limit: Some(20),
+            },
+        );
+
+        assert_eq!(
+            builder.sql(),
+            concat!(
+                " WHERE TRUE AND task_id = $1 AND actor = $2",
+                " AND occurred_at >= $3 AND occurred_at < $4",
+                " ORDER BY occurred_at ASC, event_id ASC LIMIT $5"
+            )
+        );
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_compares_timestamps_chronologically() {
+        let mut builder = QueryBuilder::<sqlx::Sqlite>::new("");
+        push_query(
+            &mut builder,
+            &TaskQuery {
+                filter: Some(TaskFilter::DueBefore { before: at(8) }),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            builder.sql(),
+            concat!(
+                " WHERE deleted_at IS NULL AND (julianday(due_date) < julianday(?))",
+                " ORDER BY julianday(due_date) ASC, task_id ASC LIMIT ?"
+            )
+        );
+    }
+
+    #[test]
+    fn like_wildcards_are_escaped() {
+        assert_eq!(escape_like(r"50%_off\"), r"50\%\_off\\");
+    }
+}
@@ -0,0 +1,406 @@
+use crate::db::interface::{self, RepositoryErr};
+use crate::domain::task::event::{TaskEvent, TaskEventQuery};
+use crate::domain::task::filter::TaskQuery;
+use crate::domain::task::model::Task;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use uuid::Uuid;
+
+/// Thread-safe, in-process task store implementing the same contract as the Postgres repository:
+/// `save` upserts (keeping the original `create_date`), deleted tasks are hidden unless a query
+/// asks for them, missing ids surface as `RepositoryErr::NotFound`, and filtered results follow
+/// `TaskQuery::compare`. Clones share the same underlying store.
+#[derive(Clone, Default)]
+pub struct Repository {
+    tasks: Arc<RwLock<HashMap<Uuid, Task>>>,
+    events: Arc<RwLock<Vec<TaskEvent>>>,
+}
+
+impl Repository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl interface::Repository<Task> for Repository {
+    type Filter = TaskQuery;
+
+    async fn save(&self, mut new: Task) -> anyhow::Result<Task> {
+        let mut tasks = self.tasks.write().expect("task store lock poisoned");
+        if let Some(existing) = tasks.get(&new.id) {
+            new.create_date = existing.create_date;
+        }
+        tasks.insert(new.id, new.clone());
+        Ok(new)
+    }
+
+    async fn retrieve_by_id(&self, id: Uuid) -> anyhow::Result<Task> {
+        let tasks = self.tasks.read().expect("task store lock poisoned");
+        Ok(tasks
+            .get(&id)
+            .filter(|task| task.deleted_at.is_none())
+            .cloned()
+            .ok_or(RepositoryErr::NotFound { id })?)
+    }
+
+    async fn retrieve_by_id_including_deleted(&self, id: Uuid) -> anyhow::Result<Task> {
+        let tasks = self.tasks.read().expect("task store lock poisoned");
+        Ok(tasks
+            .get(&id)
+            .cloned()
+            .ok_or(RepositoryErr::NotFound { id })?)
+    }
+
+    async fn retrieve_by_filter(&self, filter: TaskQuery) -> anyhow::Result<Vec<Task>> {
+        let tasks = self.tasks.read().expect("task store lock poisoned");
+        let mut matching: Vec<Task> = tasks
+            .values()
+            .filter(|task| filter.matches(task))
+            .cloned()
+            .collect();
+        matching.sort_by(|a, b| filter.compare(a, b));
+        matching.truncate(filter.effective_limit() as usize);
+        Ok(matching)
+    }
+}
+
+#[async_trait]
+impl interface::EventLog<Task, TaskEvent> for Repository {
+    type EventFilter = TaskEventQuery;
+
+    async fn save_with_event(&self, mut new: Task, event: TaskEvent) -> anyhow::Result<Task> {
+        // Hold both locks so readers never see the task without its event or vice versa.
+        let mut tasks = self.tasks.write().expect("task store lock poisoned");
+        let mut events = self.events.write().expect("task event log lock poisoned");
+        if let Some(existing) = tasks.get(&new.id) {
+            new.create_date = existing.create_date;
+        }
+        tasks.insert(new.id, new.clone());
+        events.push(event);
+        Ok(new)
+    }
+
+    async fn update_with_event<F>(
+        &self,
+        id: Uuid,
+        include_deleted: bool,
+        change: F,
+    ) -> anyhow::Result<Task>
+    where
+        F: FnOnce(Task) -> anyhow::Result<(Task, TaskEvent)> + Send,
+    {
+        let mut tasks = self.tasks.write().expect("task store lock poisoned");
+        let mut events = self.events.write().expect("task event log lock poisoned");
+        let existing = tasks
+            .get(&id)
+            .filter(|task| include_deleted || task.deleted_at.is_none())
+            .cloned()
+            .ok_or(RepositoryErr::NotFound { id })?;
+        let create_date = existing.create_date;
+        let (mut new, event) = change(existing)?;
+        new.create_date = create_date;
+        tasks.insert(new.id, new.clone());
+        events.push(event);
+        Ok(new)
+    }
+
+    async fn retrieve_events(&self, filter: TaskEventQuery) -> anyhow::Result<Vec<TaskEvent>> {
+        let events = self.events.read().expect("task event log lock poisoned");
+        let mut matching: Vec<TaskEvent> = events
+            .iter()
+            .filter(|event| filter.matches(event))
+            .cloned()
+            .collect();
+        matching.sort_by(|a, b| a.occurred_at.cmp(&b.occurred_at).then(a.id.cmp(&b.id)));
+        if let Some(limit) = filter.limit {
+            matching.truncate(limit as usize);
+        }
+        Ok(matching)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::interface::{EventLog as _, Repository as _};
+    use crate::domain::task::event::ChangeOrigin;
+    use crate::domain::task::filter::{
+        SortDirection, SortField, TaskFilter, TaskSort, MAX_QUERY_LIMIT,
+    };
+    use crate::domain::task::model::TaskStatus;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, day, 12, 0, 0).unwrap()
+    }
+
+    fn task(description: &str, assignee: &str, due_day: u32) -> Task {
+        Task {
+            id: Uuid::new_v4(),
+            description: description.to_string(),
+            create_date: at(1),
+            due_date: at(due_day),
+            assignee: assignee.to_string(),
+            status: TaskStatus::Open,
+            completed_at: None,
+            deleted_at: None,
+        }
+    }
+
+    async fn seeded(tasks: Vec<Task>) -> Repository {
+        let repo = Repository::new();
+        for task in tasks {
+            repo.save(task).await.unwrap();
+        }
+        repo
+    }
+
+    fn descriptions(tasks: &[Task]) -> Vec<&str> {
+        tasks.iter().map(|task| task.description.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn filters_combine() {
+        let mut done = task("Mow the lawn", "Sam", 3);
+        done.status = TaskStatus::Done;
+        let repo = seeded(vec![
+            task("Take the bins out", "sam", 5),
+            task("Water the plants", "Alex", 4),
+            done,
+        ])
+        .await;
+
+        let found = repo
+            .retrieve_by_filter(TaskQuery {
+                filter: Some(TaskFilter::And {
+                    filters: vec![
+                        TaskFilter::AssigneeEquals {
+                            assignee: String::from("SAM"),
+                        },
+                        TaskFilter::unfinished(),
+                    ],
+                }),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(descriptions(&found), vec!["Take the bins out"]);
+
+        let found = repo
+            .retrieve_by_filter(TaskQuery {
+                filter: Some(TaskFilter::Or {
+                    filters: vec![
+                        TaskFilter::DescriptionContains {
+                            text: String::from("PLANTS"),
+                        },
+                        TaskFilter::DueBefore { before: at(4) },
+                    ],
+                }),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            descriptions(&found),
+            vec!["Mow the lawn", "Water the plants"]
+        );
+    }
+
+    #[tokio::test]
+    async fn sorts_by_requested_keys_case_insensitively() {
+        let repo = seeded(vec![
+            task("b task", "bob", 3),
+            task("A task", "Alex", 5),
+            task("c task", "alex", 4),
+        ])
+        .await;
+
+        let found = repo
+            .retrieve_by_filter(TaskQuery {
+                sort: vec![TaskSort {
+                    field: SortField::Description,
+                    direction: SortDirection::Ascending,
+                }],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(descriptions(&found), vec!["A task", "b task", "c task"]);
+
+        // Equal assignees (ignoring case) fall back to the due date.
+        let found = repo
+            .retrieve_by_filter(TaskQuery {
+                sort: vec![TaskSort {
+                    field: SortField::Assignee,
+                    direction: SortDirection::Descending,
+                }],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(descriptions(&found), vec!["b task", "c task", "A task"]);
+    }
+
+    #[tokio::test]
+    async fn defaults_to_soonest_due_first() {
+        let repo = seeded(vec![
+            task("later", "Sam", 9),
+            task("sooner", "Sam", 2),
+            task("middle", "Sam", 5),
+        ])
+        .await;
+
+        let found = repo.retrieve_by_filter(TaskQuery::default()).await.unwrap();
+        assert_eq!(descriptions(&found), vec!["sooner", "middle", "later"]);
+    }
+
+    #[tokio::test]
+    async fn caps_the_limit() {
+        let repo = Repository::new();
+        for i in 0..(MAX_QUERY_LIMIT + 5) {
+            let mut task = task("chore", "Sam", 1);
+            task.due_date += Duration::minutes(i64::from(i));
+            repo.save(task).await.unwrap();
+        }
+
+        let capped = repo
+            .retrieve_by_filter(TaskQuery {
+                limit: Some(MAX_QUERY_LIMIT * 10),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(capped.len(), MAX_QUERY_LIMIT as usize);
+
+        let limited = repo
+            .retrieve_by_filter(TaskQuery {
+                limit: Some(3),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(limited.len(), 3);
+        let ids = |tasks: &[Task]| tasks.iter().map(|task| task.id).collect::<Vec<_>>();
+        assert_eq!(ids(&limited), ids(&capped[..3]));
+    }
+
+    #[tokio::test]
+    async fn missing_ids_are_not_found() {
+        let repo = Repository::new();
+        let id = Uuid::new_v4();
+
+        let e = repo.retrieve_by_id(id).await.unwrap_err();
+
+        assert!(matches!(
+            e.downcast_ref::<RepositoryErr>(),
+            Some(RepositoryErr::NotFound { id: missing }) if *missing == id
+        ));
+    }
+
+    #[tokio::test]
+    async fn deleted_tasks_are_hidden_unless_asked_for() {
+        let kept = task("Take the bins out", "Sam", 3);
+        let mut deleted = task("Water the plants", "Sam", 4);
+        deleted.deleted_at = Some(at(2));
+        let deleted_id = deleted.id;
+        let repo = seeded(vec![kept, deleted]).await;
+
+        let e = repo.retrieve_by_id(deleted_id).await.unwrap_err();
+        assert!(matches!(
+            e.downcast_ref::<RepositoryErr>(),
+            Some(RepositoryErr::NotFound { .. })
+        ));
+        let found = repo
+            .retrieve_by_id_including_deleted(deleted_id)
+            .await
+            .unwrap();
+        assert_eq!(found.description, "Water the plants");
+        let visible = repo.retrieve_by_filter(TaskQuery::default()).await.unwrap();
+        assert_eq!(descriptions(&visible), vec!["Take the bins out"]);
+        let all = repo
+            .retrieve_by_filter(TaskQuery {
+                include_deleted: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            descriptions(&all),
+            vec!["Take the bins out", "Water the plants"]
+        );
+    }
+
+    #[tokio::test]
+    async fn saving_again_keeps_the_create_date() {
+        let original = task("Take the bins out", "Sam", 3);
+        let repo = seeded(vec![original.clone()]).await;
+        let mut updated = original.clone();
+        updated.create_date = at(20);
+        updated.description = String::from("Take the bins and recycling out");
+
+        let saved = repo.save(updated).await.unwrap();
+
+        assert_eq!(saved.create_date, original.create_date);
+        assert_eq!(
+            repo.retrieve_by_id(original.id).await.unwrap().description,
+            "Take the bins and recycling out"
+        );
+    }
+
+    #[tokio::test]
+    async fn updates_record_the_snapshot_they_changed() {
+        let original = task("Take the bins out", "Sam", 3);
+        let id = original.id;
+        let repo = seeded(vec![original]).await;
+        let origin = ChangeOrigin {
+            actor: String::from("Sam"),
+            source_text: String::from("the bins are done"),
+            intent: String::from("CompleteTask"),
+            params: serde_json::Value::Null,
+        };
+
+        let saved = repo
+            .update_with_event(id, false, |existing| {
+                let mut done = existing.clone();
+                done.status = TaskStatus::Done;
+                let event = TaskEvent::new(&origin, Some(existing), done.clone());
+                Ok((done, event))
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(saved.status, TaskStatus::Done);
+        let events = repo
+            .retrieve_events(TaskEventQuery {
+                task_id: Some(id),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].before.as_ref().map(|task| task.status),
+            Some(TaskStatus::Open)
+        );
+    }
+
+    #[tokio::test]
+    async fn updates_skip_deleted_tasks() {
+        let mut deleted = task("Water the plants", "Sam", 4);
+        deleted.deleted_at = Some(at(2));
+        let id = deleted.id;
+        let repo = seeded(vec![deleted]).await;
+
+        let e = repo
+            .update_with_event(id, false, |_| unreachable!("deleted tasks are not updated"))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            e.downcast_ref::<RepositoryErr>(),
+            Some(RepositoryErr::NotFound { id: missing }) if *missing == id
+        ));
+    }
+}
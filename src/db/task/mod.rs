@@ -1,71 +1,298 @@
+use crate::domain::task::event::{TaskEvent, TaskEventQuery};
+use crate::domain::task::filter::TaskQuery;
 use crate::domain::task::model::Task;
 use async_trait::async_trait;
+use sqlx::{Postgres, QueryBuilder};
 use uuid::Uuid;
 
-use super::interface;
+use super::{interface, Database};
+use interface::{EventLog as _, Repository as _};
+
+mod filter;
+pub mod memory;
+pub mod row;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+use row::{TaskEventRow, TaskRow, TASK_COLUMNS, TASK_EVENT_COLUMNS};
 
-// TODO: decouple db schema changes from domain model changes by introducing db layer-specific
-// entity struct
 #[derive(Clone)]
 pub struct Repository<DB: sqlx::Database> {
     db_pool: sqlx::Pool<DB>,
 }
 #[async_trait]
-impl<DB: sqlx::Database> interface::Repository<Task> for Repository<DB> {
+impl interface::Repository<Task> for Repository<Postgres> {
+    type Filter = TaskQuery;
+
     async fn save(&self, new: Task) -> anyhow::Result<Task> {
-        Ok(sqlx::query!(
-            r#"
-            INSERT INTO tasks (task_id, description, create_date, due_date, assignee)
-            VALUES ($1, $2, $3, $4, $5)
-            RETURNING task_id
-            "#,
-            self.task_id,
-            self.description,
-            self.create_date,
-            self.due_date,
-            self.assignee
-        )
-        .fetch_one(&self.db_pool)
-        .await?)
+        upsert(&self.db_pool, new).await
     }
 
     async fn retrieve_by_id(&self, id: Uuid) -> anyhow::Result<Task> {
-        let task = sqlx::query_as!(
-            Task,
+        let row = sqlx::query_as::<_, TaskRow>(&format!(
             r#"
-            SELECT task_id, description, create_date, due_date, assignee
+            SELECT {TASK_COLUMNS}
             FROM tasks
-            WHERE task_id = $1
-            "#,
-            task_id
-        )
+            WHERE task_id = $1 AND deleted_at IS NULL
+            "#
+        ))
+        .bind(id)
+        .fetch_optional(&self.db_pool)
+        .await?
+        .ok_or(interface::RepositoryErr::NotFound { id })?;
+        Task::try_from(row)
+    }
+
+    async fn retrieve_by_id_including_deleted(&self, id: Uuid) -> anyhow::Result<Task> {
+        let row = sqlx::query_as::<_, TaskRow>(&format!(
+            "SELECT {TASK_COLUMNS} FROM tasks WHERE task_id = $1"
+        ))
+        .bind(id)
         .fetch_optional(&self.db_pool)
-        .await?;
+        .await?
+        .ok_or(interface::RepositoryErr::NotFound { id })?;
+        Task::try_from(row)
+    }
+
+    async fn retrieve_by_filter(&self, filter: TaskQuery) -> anyhow::Result<Vec<Task>> {
+        let mut builder =
+            QueryBuilder::<Postgres>::new(format!("SELECT {TASK_COLUMNS} FROM tasks"));
+        filter::push_query(&mut builder, &filter);
+        let rows = builder
+            .build_query_as::<TaskRow>()
+            .fetch_all(&self.db_pool)
+            .await?;
+        rows.into_iter().map(Task::try_from).collect()
+    }
+}
+/// Inserts the task, or overwrites everything but `create_date` if it already exists.
+async fn upsert<'e>(
+    executor: impl sqlx::Executor<'e, Database = Postgres>,
+    new: Task,
+) -> anyhow::Result<Task> {
+    let new = TaskRow::from(new);
+    let row = sqlx::query_as::<_, TaskRow>(&format!(
+        r#"
+        INSERT INTO tasks ({TASK_COLUMNS})
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (task_id) DO UPDATE SET
+            description = EXCLUDED.description,
+            due_date = EXCLUDED.due_date,
+            assignee = EXCLUDED.assignee,
+            status = EXCLUDED.status,
+            completed_at = EXCLUDED.completed_at,
+            deleted_at = EXCLUDED.deleted_at
+        RETURNING {TASK_COLUMNS}
+        "#
+    ))
+    .bind(new.task_id)
+    .bind(new.description)
+    .bind(new.create_date)
+    .bind(new.due_date)
+    .bind(new.assignee)
+    .bind(new.status)
+    .bind(new.completed_at)
+    .bind(new.deleted_at)
+    .fetch_one(executor)
+    .await?;
+    Task::try_from(row)
+}
 
-        Ok(task)
+#[async_trait]
+impl interface::EventLog<Task, TaskEvent> for Repository<Postgres> {
+    type EventFilter = TaskEventQuery;
+
+    async fn save_with_event(&self, new: Task, event: TaskEvent) -> anyhow::Result<Task> {
+        let mut tx = self.db_pool.begin().await?;
+        let saved = upsert(&mut *tx, new).await?;
+        insert_event(&mut *tx, event).await?;
+        tx.commit().await?;
+        Ok(saved)
     }
 
-    async fn delete_by_id(&self, id: Uuid) -> anyhow::Result<Task> {
-        let query = r#"
-            DELETE FROM tasks
-            WHERE task_id = $1
-            RETURNING task_id, description, create_date, due_date, assignee
-            "#;
-        Ok(sqlx::query_as!(
-            Task,
+    async fn update_with_event<F>(
+        &self,
+        id: Uuid,
+        include_deleted: bool,
+        change: F,
+    ) -> anyhow::Result<Task>
+    where
+        F: FnOnce(Task) -> anyhow::Result<(Task, TaskEvent)> + Send,
+    {
+        let mut tx = self.db_pool.begin().await?;
+        let live_only = if include_deleted {
+            ""
+        } else {
+            "AND deleted_at IS NULL"
+        };
+        // The row lock holds off concurrent updates until this transaction ends.
+        let row = sqlx::query_as::<_, TaskRow>(&format!(
             r#"
-            DELETE FROM tasks
-            WHERE task_id = $1
-            RETURNING task_id, description, create_date, due_date, assignee
-            "#,
-            task_id
-        )
-        .fetch_optional(&self.db_pool)
-        .await?)
+            SELECT {TASK_COLUMNS}
+            FROM tasks
+            WHERE task_id = $1 {live_only}
+            FOR UPDATE
+            "#
+        ))
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(interface::RepositoryErr::NotFound { id })?;
+        let (new, event) = change(Task::try_from(row)?)?;
+        let saved = upsert(&mut *tx, new).await?;
+        insert_event(&mut *tx, event).await?;
+        tx.commit().await?;
+        Ok(saved)
+    }
+
+    async fn retrieve_events(&self, filter: TaskEventQuery) -> anyhow::Result<Vec<TaskEvent>> {
+        let mut builder =
+            QueryBuilder::<Postgres>::new(format!("SELECT {TASK_EVENT_COLUMNS} FROM task_events"));
+        filter::push_event_query(&mut builder, &filter);
+        let rows = builder
+            .build_query_as::<TaskEventRow>()
+            .fetch_all(&self.db_pool)
+            .await?;
+        rows.into_iter().map(TaskEvent::try_from).collect()
     }
 }
+
+async fn insert_event<'e>(
+    executor: impl sqlx::Executor<'e, Database = Postgres>,
+    event: TaskEvent,
+) -> anyhow::Result<()> {
+    let event = TaskEventRow::try_from(event)?;
+    sqlx::query(&format!(
+        r#"
+        INSERT INTO task_events ({TASK_EVENT_COLUMNS})
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#
+    ))
+    .bind(event.event_id)
+    .bind(event.task_id)
+    .bind(event.occurred_at)
+    .bind(event.actor)
+    .bind(event.source_text)
+    .bind(event.intent)
+    .bind(event.params)
+    .bind(event.before)
+    .bind(event.after)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
 impl<T: sqlx::Database> Repository<T> {
     pub fn new(pool: sqlx::Pool<T>) -> Self {
         Self { db_pool: pool }
     }
 }
+
+/// Task storage matching the database selected at startup, so the service and bot can stay
+/// generic over a single repository type.
+#[derive(Clone)]
+pub enum AnyRepository {
+    Postgres(Repository<Postgres>),
+    #[cfg(feature = "sqlite")]
+    Sqlite(Repository<sqlx::Sqlite>),
+    Memory(memory::Repository),
+}
+
+impl AnyRepository {
+    /// Task repository over whichever database was connected at startup. Each call on a
+    /// `Database::Memory` creates a fresh, empty store.
+    pub fn new(database: &Database) -> Self {
+        match database {
+            Database::Postgres(pool) => AnyRepository::Postgres(Repository::new(pool.clone())),
+            #[cfg(feature = "sqlite")]
+            Database::Sqlite(pool) => AnyRepository::Sqlite(Repository::new(pool.clone())),
+            Database::Memory => AnyRepository::Memory(memory::Repository::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl interface::Repository<Task> for AnyRepository {
+    type Filter = TaskQuery;
+
+    async fn save(&self, new: Task) -> anyhow::Result<Task> {
+        match self {
+            AnyRepository::Postgres(repo) => repo.save(new).await,
+            #[cfg(feature = "sqlite")]
+            AnyRepository::Sqlite(repo) => repo.save(new).await,
+            AnyRepository::Memory(repo) => repo.save(new).await,
+        }
+    }
+
+    async fn retrieve_by_id(&self, id: Uuid) -> anyhow::Result<Task> {
+        match self {
+            AnyRepository::Postgres(repo) => repo.retrieve_by_id(id).await,
+            #[cfg(feature = "sqlite")]
+            AnyRepository::Sqlite(repo) => repo.retrieve_by_id(id).await,
+            AnyRepository::Memory(repo) => repo.retrieve_by_id(id).await,
+        }
+    }
+
+    async fn retrieve_by_id_including_deleted(&self, id: Uuid) -> anyhow::Result<Task> {
+        match self {
+            AnyRepository::Postgres(repo) => repo.retrieve_by_id_including_deleted(id).await,
+            #[cfg(feature = "sqlite")]
+            AnyRepository::Sqlite(repo) => repo.retrieve_by_id_including_deleted(id).await,
+            AnyRepository::Memory(repo) => repo.retrieve_by_id_including_deleted(id).await,
+        }
+    }
+
+    async fn retrieve_by_filter(&self, filter: TaskQuery) -> anyhow::Result<Vec<Task>> {
+        match self {
+            AnyRepository::Postgres(repo) => repo.retrieve_by_filter(filter).await,
+            #[cfg(feature = "sqlite")]
+            AnyRepository::Sqlite(repo) => repo.retrieve_by_filter(filter).await,
+            AnyRepository::Memory(repo) => repo.retrieve_by_filter(filter).await,
+        }
+    }
+}
+#[async_trait]
+impl interface::EventLog<Task, TaskEvent> for AnyRepository {
+    type EventFilter = TaskEventQuery;
+
+    async fn save_with_event(&self, new: Task, event: TaskEvent) -> anyhow::Result<Task> {
+        match self {
+            AnyRepository::Postgres(repo) => repo.save_with_event(new, event).await,
+            #[cfg(feature = "sqlite")]
+            AnyRepository::Sqlite(repo) => repo.save_with_event(new, event).await,
+            AnyRepository::Memory(repo) => repo.save_with_event(new, event).await,
+        }
+    }
+
+    async fn update_with_event<F>(
+        &self,
+        id: Uuid,
+        include_deleted: bool,
+        change: F,
+    ) -> anyhow::Result<Task>
+    where
+        F: FnOnce(Task) -> anyhow::Result<(Task, TaskEvent)> + Send,
+    {
+        match self {
+            AnyRepository::Postgres(repo) => {
+                repo.update_with_event(id, include_deleted, change).await
+            }
+            #[cfg(feature = "sqlite")]
+            AnyRepository::Sqlite(repo) => {
+                repo.update_with_event(id, include_deleted, change).await
+            }
+            AnyRepository::Memory(repo) => {
+                repo.update_with_event(id, include_deleted, change).await
+            }
+        }
+    }
+
+    async fn retrieve_events(&self, filter: TaskEventQuery) -> anyhow::Result<Vec<TaskEvent>> {
+        match self {
+            AnyRepository::Postgres(repo) => repo.retrieve_events(filter).await,
+            #[cfg(feature = "sqlite")]
+            AnyRepository::Sqlite(repo) => repo.retrieve_events(filter).await,
+            AnyRepository::Memory(repo) => repo.retrieve_events(filter).await,
+        }
+    }
+}
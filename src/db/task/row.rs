@@ -0,0 +1,107 @@
+use crate::domain::task::event::TaskEvent;
+use crate::domain::task::model::{Task, TaskStatus};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// Storage-layer shape of a `tasks` row. Column names and storage types live here so schema
+/// changes (new nullable columns, renames, encodings) stop at the conversions below instead of
+/// rippling into the domain model and the LLM-facing schemas derived from it.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct TaskRow {
+    pub task_id: Uuid,
+    pub description: String,
+    pub create_date: DateTime<Utc>,
+    pub due_date: DateTime<Utc>,
+    pub assignee: String,
+    pub status: String,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// Column list matching `TaskRow`'s fields, for `SELECT`/`RETURNING` clauses.
+pub const TASK_COLUMNS: &str =
+    "task_id, description, create_date, due_date, assignee, status, completed_at, deleted_at";
+
+impl From<Task> for TaskRow {
+    fn from(task: Task) -> Self {
+        Self {
+            task_id: task.id,
+            description: task.description,
+            create_date: task.create_date,
+            due_date: task.due_date,
+            assignee: task.assignee,
+            status: task.status.as_str().to_string(),
+            completed_at: task.completed_at,
+            deleted_at: task.deleted_at,
+        }
+    }
+}
+
+impl TryFrom<TaskRow> for Task {
+    type Error = anyhow::Error;
+    fn try_from(row: TaskRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.task_id,
+            description: row.description,
+            create_date: row.create_date,
+            due_date: row.due_date,
+            assignee: row.assignee,
+            status: row.status.parse::<TaskStatus>()?,
+            completed_at: row.completed_at,
+            deleted_at: row.deleted_at,
+        })
+    }
+}
+
+/// Storage-layer shape of a `task_events` row. Snapshots are stored as JSON so the log survives
+/// later changes to the `tasks` columns.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct TaskEventRow {
+    pub event_id: Uuid,
+    pub task_id: Uuid,
+    pub occurred_at: DateTime<Utc>,
+    pub actor: String,
+    pub source_text: String,
+    pub intent: String,
+    pub params: serde_json::Value,
+    pub before: Option<serde_json::Value>,
+    pub after: serde_json::Value,
+}
+
+/// Column list matching `TaskEventRow`'s fields, for `SELECT`/`INSERT` clauses.
+pub const TASK_EVENT_COLUMNS: &str =
+    "event_id, task_id, occurred_at, actor, source_text, intent, params, before, after";
+
+impl TryFrom<TaskEvent> for TaskEventRow {
+    type Error = anyhow::Error;
+    fn try_from(event: TaskEvent) -> Result<Self, Self::Error> {
+        Ok(Self {
+            event_id: event.id,
+            task_id: event.task_id,
+            occurred_at: event.occurred_at,
+            actor: event.actor,
+            source_text: event.source_text,
+            intent: event.intent,
+            params: event.params,
+            before: event.before.map(serde_json::to_value).transpose()?,
+            after: serde_json::to_value(event.after)?,
+        })
+    }
+}
+
+impl TryFrom<TaskEventRow> for TaskEvent {
+    type Error = anyhow::Error;
+    fn try_from(row: TaskEventRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.event_id,
+            task_id: row.task_id,
+            occurred_at: row.occurred_at,
+            actor: row.actor,
+            source_text: row.source_text,
+            intent: row.intent,
+            params: row.params,
+            before: row.before.map(serde_json::from_value).transpose()?,
+            after: serde_json::from_value(row.after)?,
+        })
+    }
+}
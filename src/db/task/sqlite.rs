@@ -0,0 +1,172 @@
+use crate::db::interface::{self, RepositoryErr};
+use crate::domain::task::event::{TaskEvent, TaskEventQuery};
+use crate::domain::task::filter::TaskQuery;
+use crate::domain::task::model::Task;
+use async_trait::async_trait;
+use sqlx::{QueryBuilder, Sqlite};
+use uuid::Uuid;
+
+use super::row::{TaskEventRow, TaskRow, TASK_COLUMNS, TASK_EVENT_COLUMNS};
+use super::{filter, Repository};
+
+#[async_trait]
+impl interface::Repository<Task> for Repository<Sqlite> {
+    type Filter = TaskQuery;
+
+    async fn save(&self, new: Task) -> anyhow::Result<Task> {
+        upsert(&self.db_pool, new).await
+    }
+
+    async fn retrieve_by_id(&self, id: Uuid) -> anyhow::Result<Task> {
+        let row = sqlx::query_as::<_, TaskRow>(&format!(
+            r#"
+            SELECT {TASK_COLUMNS}
+            FROM tasks
+            WHERE task_id = ? AND deleted_at IS NULL
+            "#
+        ))
+        .bind(id)
+        .fetch_optional(&self.db_pool)
+        .await?
+        .ok_or(RepositoryErr::NotFound { id })?;
+        Task::try_from(row)
+    }
+
+    async fn retrieve_by_id_including_deleted(&self, id: Uuid) -> anyhow::Result<Task> {
+        let row = sqlx::query_as::<_, TaskRow>(&format!(
+            "SELECT {TASK_COLUMNS} FROM tasks WHERE task_id = ?"
+        ))
+        .bind(id)
+        .fetch_optional(&self.db_pool)
+        .await?
+        .ok_or(RepositoryErr::NotFound { id })?;
+        Task::try_from(row)
+    }
+
+    async fn retrieve_by_filter(&self, filter: TaskQuery) -> anyhow::Result<Vec<Task>> {
+        let mut builder = QueryBuilder::<Sqlite>::new(format!("SELECT {TASK_COLUMNS} FROM tasks"));
+        filter::push_query(&mut builder, &filter);
+        let rows = builder
+            .build_query_as::<TaskRow>()
+            .fetch_all(&self.db_pool)
+            .await?;
+        rows.into_iter().map(Task::try_from).collect()
+    }
+}
+
+/// Inserts the task, or overwrites everything but `create_date` if it already exists.
+async fn upsert<'e>(
+    executor: impl sqlx::Executor<'e, Database = Sqlite>,
+    new: Task,
+) -> anyhow::Result<Task> {
+    let new = TaskRow::from(new);
+    let row = sqlx::query_as::<_, TaskRow>(&format!(
+        r#"
+        INSERT INTO tasks ({TASK_COLUMNS})
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT (task_id) DO UPDATE SET
+            description = excluded.description,
+            due_date = excluded.due_date,
+            assignee = excluded.assignee,
+            status = excluded.status,
+            completed_at = excluded.completed_at,
+            deleted_at = excluded.deleted_at
+        RETURNING {TASK_COLUMNS}
+        "#
+    ))
+    .bind(new.task_id)
+    .bind(new.description)
+    .bind(new.create_date)
+    .bind(new.due_date)
+    .bind(new.assignee)
+    .bind(new.status)
+    .bind(new.completed_at)
+    .bind(new.deleted_at)
+    .fetch_one(executor)
+    .await?;
+    Task::try_from(row)
+}
+
+async fn insert_event<'e>(
+    executor: impl sqlx::Executor<'e, Database = Sqlite>,
+    event: TaskEvent,
+) -> anyhow::Result<()> {
+    let event = TaskEventRow::try_from(event)?;
+    sqlx::query(&format!(
+        r#"
+        INSERT INTO task_events ({TASK_EVENT_COLUMNS})
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#
+    ))
+    .bind(event.event_id)
+    .bind(event.task_id)
+    .bind(event.occurred_at)
+    .bind(event.actor)
+    .bind(event.source_text)
+    .bind(event.intent)
+    .bind(event.params)
+    .bind(event.before)
+    .bind(event.after)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+#[async_trait]
+impl interface::EventLog<Task, TaskEvent> for Repository<Sqlite> {
+    type EventFilter = TaskEventQuery;
+
+    async fn save_with_event(&self, new: Task, event: TaskEvent) -> anyhow::Result<Task> {
+        let mut tx = self.db_pool.begin().await?;
+        let saved = upsert(&mut *tx, new).await?;
+        insert_event(&mut *tx, event).await?;
+        tx.commit().await?;
+        Ok(saved)
+    }
+
+    async fn update_with_event<F>(
+        &self,
+        id: Uuid,
+        include_deleted: bool,
+        change: F,
+    ) -> anyhow::Result<Task>
+    where
+        F: FnOnce(Task) -> anyhow::Result<(Task, TaskEvent)> + Send,
+    {
+        // SQLite has no row locks, but it allows one writer at a time: if another transaction
+        // writes the task after this read, this one fails to commit instead of overwriting it.
+        let mut tx = self.db_pool.begin().await?;
+        let live_only = if include_deleted {
+            ""
+        } else {
+            "AND deleted_at IS NULL"
+        };
+        let row = sqlx::query_as::<_, TaskRow>(&format!(
+            r#"
+            SELECT {TASK_COLUMNS}
+            FROM tasks
+            WHERE task_id = ? {live_only}
+            "#
+        ))
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(RepositoryErr::NotFound { id })?;
+        let (new, event) = change(Task::try_from(row)?)?;
+        let saved = upsert(&mut *tx, new).await?;
+        insert_event(&mut *tx, event).await?;
+        tx.commit().await?;
+        Ok(saved)
+    }
+
+    async fn retrieve_events(&self, filter: TaskEventQuery) -> anyhow::Result<Vec<TaskEvent>> {
+        let mut builder =
+            QueryBuilder::<Sqlite>::new(format!("SELECT {TASK_EVENT_COLUMNS} FROM task_events"));
+        filter::push_event_query(&mut builder, &filter);
+        let rows = builder
+            .build_query_as::<TaskEventRow>()
+            .fetch_all(&self.db_pool)
+            .await?;
+        rows.into_iter().map(TaskEvent::try_from).collect()
+    }
+}
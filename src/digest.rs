@@ -0,0 +1,216 @@
+use crate::db;
+use crate::db::digest::{DigestKind, Schedule};
+use crate::domain::task::filter::{TaskFilter, TaskQuery, MAX_QUERY_LIMIT};
+use crate::domain::task::model::Task;
+use crate::domain::task::service::TaskDataFlows;
+use crate::output::telegram::{self, Grouping};
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use std::sync::Arc;
+use teloxide::prelude::*;
+use teloxide::types::ParseMode;
+use tokio::time::MissedTickBehavior;
+
+/// The weekly digest goes out on this day.
+pub const WEEKLY_DIGEST_DAY: Weekday = Weekday::Sun;
+
+/// Parses a digest send time written as `HH:MM`.
+pub fn parse_send_time(text: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(text.trim(), "%H:%M").ok()
+}
+
+/// Sends each chat its scheduled digests: every morning the tasks of the assignees linked to it
+/// (see `/remindme`) that are due today or overdue, and every Sunday a review of the week. A
+/// digest missed while the bot was down goes out late the same day, but never twice.
+pub struct Scheduler<S> {
+    pub task_data_flows: S,
+    pub digests: Arc<db::digest::Store>,
+    /// Which assignees each chat's daily digest covers.
+    pub reminders: Arc<db::reminder::Store>,
+    pub poll_interval: std::time::Duration,
+}
+
+impl<S: TaskDataFlows> Scheduler<S> {
+    /// Runs until the process exits. A failed round is logged and retried on the next tick.
+    pub async fn run(self, bot: Bot) {
+        let mut ticks = tokio::time::interval(self.poll_interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticks.tick().await;
+            if let Err(e) = self.send_due(&bot).await {
+                log::error!("Sending digests failed: {:?}", e);
+            }
+        }
+    }
+
+    async fn send_due(&self, bot: &Bot) -> anyhow::Result<()> {
+        let now = Utc::now();
+        for schedule in self.digests.all().await? {
+            let local_now = now.with_timezone(&schedule.timezone);
+            let today = local_now.date_naive();
+            let is_due = local_now.time() >= schedule.send_time
+                && schedule.last_sent_on.map_or(true, |last| last < today)
+                && (schedule.kind == DigestKind::Daily || today.weekday() == WEEKLY_DIGEST_DAY);
+            if !is_due
+                || !self
+                    .digests
+                    .mark_sent(schedule.chat_id, schedule.kind, today)
+                    .await?
+            {
+                continue;
+            }
+            let sent = match schedule.kind {
+                DigestKind::Daily => self.send_daily(bot, &schedule, local_now).await,
+                DigestKind::Weekly => self.send_weekly(bot, &schedule, local_now).await,
+            };
+            if let Err(e) = sent {
+                log::warn!(
+                    "Could not send the {} digest to chat {}: {:?}",
+                    schedule.kind.as_str(),
+                    schedule.chat_id,
+                    e
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_daily(
+        &self,
+        bot: &Bot,
+        schedule: &Schedule,
+        now: DateTime<Tz>,
+    ) -> anyhow::Result<()> {
+        let assignees = self.reminders.linked_assignees(schedule.chat_id).await?;
+        if assignees.is_empty() {
+            return Ok(());
+        }
+        let tomorrow = (now.date_naive() + Duration::days(1))
+            .and_hms_opt(0, 0, 0)
+            .expect("midnight is a valid time");
+        let end_of_today = schedule
+            .timezone
+            .from_local_datetime(&tomorrow)
+            .earliest()
+            // Only when a DST change skips midnight itself.
+            .unwrap_or(now + Duration::days(1));
+        let tasks = self
+            .task_data_flows
+            .retrieve_tasks(TaskQuery {
+                filter: Some(TaskFilter::And {
+                    filters: vec![
+                        TaskFilter::AssigneeIn { assignees },
+                        TaskFilter::unfinished(),
+                        TaskFilter::DueBefore {
+                            before: end_of_today.with_timezone(&Utc),
+                        },
+                    ],
+                }),
+                limit: Some(MAX_QUERY_LIMIT),
+                ..Default::default()
+            })
+            .await?;
+        let heading = if tasks.is_empty() {
+            String::from("☀️ <b>Good morning!</b> Nothing is due today and nothing is overdue.")
+        } else {
+            String::from("☀️ <b>Good morning!</b> Here's what's due today or overdue:")
+        };
+        send_list(
+            bot,
+            schedule.chat_id,
+            heading,
+            &tasks,
+            Grouping::DueDay,
+            now,
+        )
+        .await
+    }
+
+    async fn send_weekly(
+        &self,
+        bot: &Bot,
+        schedule: &Schedule,
+        now: DateTime<Tz>,
+    ) -> anyhow::Result<()> {
+        let end = now.with_timezone(&Utc);
+        let start = end - Duration::weeks(1);
+        let completed = self
+            .task_data_flows
+            .retrieve_tasks(TaskQuery {
+                filter: Some(TaskFilter::CompletedBetween { start, end }),
+                limit: Some(MAX_QUERY_LIMIT),
+                ..Default::default()
+            })
+            .await?;
+        let slipped = self
+            .task_data_flows
+            .retrieve_tasks(TaskQuery {
+                filter: Some(TaskFilter::And {
+                    filters: vec![
+                        TaskFilter::unfinished(),
+                        TaskFilter::DueBetween { start, end },
+                    ],
+                }),
+                limit: Some(MAX_QUERY_LIMIT),
+                ..Default::default()
+            })
+            .await?;
+        bot.send_message(
+            schedule.chat_id,
+            format!(
+                "🗓 <b>Week in review:</b> {} completed, {} slipped.",
+                completed.len(),
+                slipped.len()
+            ),
+        )
+        .parse_mode(ParseMode::Html)
+        .await?;
+        if !completed.is_empty() {
+            let heading = String::from("✅ <b>Completed</b>");
+            send_list(
+                bot,
+                schedule.chat_id,
+                heading,
+                &completed,
+                Grouping::Assignee,
+                now,
+            )
+            .await?;
+        }
+        if !slipped.is_empty() {
+            let heading = String::from("⚠️ <b>Slipped</b> (past due and still open)");
+            send_list(
+                bot,
+                schedule.chat_id,
+                heading,
+                &slipped,
+                Grouping::Assignee,
+                now,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Sends `heading` on its own, so it can't push a full list message over Telegram's limit, then
+/// the tasks as rendered for query results.
+async fn send_list(
+    bot: &Bot,
+    chat_id: ChatId,
+    heading: String,
+    tasks: &[Task],
+    grouping: Grouping,
+    now: DateTime<Tz>,
+) -> anyhow::Result<()> {
+    bot.send_message(chat_id, heading)
+        .parse_mode(ParseMode::Html)
+        .await?;
+    for message in telegram::render_task_list(tasks, grouping, now) {
+        bot.send_message(chat_id, message.html)
+            .parse_mode(ParseMode::Html)
+            .reply_markup(message.keyboard)
+            .await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+use uuid::Uuid;
+
+use super::model::Task;
+
+/// Who asked for a change and how, recorded with every event it causes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeOrigin {
+    /// The Telegram user (or other client) that requested the change.
+    pub actor: String,
+    /// The original message text, or the transcript of a voice note.
+    pub source_text: String,
+    pub intent: String,
+    pub params: serde_json::Value,
+}
+
+/// One entry in the append-only change history of a task. `before` is absent for creations and
+/// `after` reflects the state written, so deletions show the task with `deleted_at` set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskEvent {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub occurred_at: DateTime<Utc>,
+    pub actor: String,
+    pub source_text: String,
+    pub intent: String,
+    pub params: serde_json::Value,
+    pub before: Option<Task>,
+    pub after: Task,
+}
+impl TaskEvent {
+    pub fn new(origin: &ChangeOrigin, before: Option<Task>, after: Task) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            task_id: after.id,
+            occurred_at: Utc::now(),
+            actor: origin.actor.clone(),
+            source_text: origin.source_text.clone(),
+            intent: origin.intent.clone(),
+            params: origin.params.clone(),
+            before,
+            after,
+        }
+    }
+}
+impl Display for TaskEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} || {} || {} || \"{}\"",
+            self.occurred_at, self.actor, self.intent, self.source_text
+        )?;
+        match &self.before {
+            Some(before) => write!(f, " || {} → {}", before, self.after),
+            None => write!(f, " || {}", self.after),
+        }
+    }
+}
+
+/// Retrieval criteria for task events. Results are ordered oldest first; unset fields don't
+/// constrain the result and an unset `limit` returns everything (for exports).
+#[derive(Debug, Clone, Default)]
+pub struct TaskEventQuery {
+    pub task_id: Option<Uuid>,
+    pub actor: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<u32>,
+}
+impl TaskEventQuery {
+    pub fn matches(&self, event: &TaskEvent) -> bool {
+        self.task_id
+            .map_or(true, |task_id| event.task_id == task_id)
+            && self
+                .actor
+                .as_ref()
+                .map_or(true, |actor| &event.actor == actor)
+            && self.since.map_or(true, |since| event.occurred_at >= since)
+            && self.until.map_or(true, |until| event.occurred_at < until)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DisplayableEventVec(pub Vec<TaskEvent>);
+impl Display for DisplayableEventVec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, event) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", event)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+use super::model::{Task, TaskStatus};
+
+/// Upper bound on the number of tasks a single query can return, regardless of what was asked for.
+pub const MAX_QUERY_LIMIT: u32 = 100;
+
+/// A typed, composable condition over tasks. The LLM emits this as JSON against the schema derived
+/// here, and storage backends compile it into their own (parameterised) query language, so no
+/// model-written query text ever reaches the database.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum TaskFilter {
+    /// Task is assigned to this person (case-insensitive).
+    AssigneeEquals { assignee: String },
+    /// Task is assigned to any of these people (case-insensitive).
+    AssigneeIn { assignees: Vec<String> },
+    /// Task is due strictly before this instant.
+    DueBefore { before: DateTime<Utc> },
+    /// Task is due at or after this instant.
+    DueAfter { after: DateTime<Utc> },
+    /// Task is due within [start, end).
+    DueBetween {
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    },
+    /// Task was created strictly before this instant.
+    CreatedBefore { before: DateTime<Utc> },
+    /// Task was created at or after this instant.
+    CreatedAfter { after: DateTime<Utc> },
+    /// Task was created within [start, end).
+    CreatedBetween {
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    },
+    /// Task was marked done within [start, end).
+    CompletedBetween {
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    },
+    /// Task description contains this text (case-insensitive).
+    DescriptionContains { text: String },
+    /// Task is in this completion status.
+    Status { status: TaskStatus },
+    /// Every one of the nested filters holds. An empty list matches every task.
+    And { filters: Vec<TaskFilter> },
+    /// At least one of the nested filters holds. An empty list matches no task.
+    Or { filters: Vec<TaskFilter> },
+    /// The nested filter does not hold.
+    Not { filter: Box<TaskFilter> },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    DueDate,
+    CreateDate,
+    Assignee,
+    Description,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TaskSort {
+    pub field: SortField,
+    #[serde(default)]
+    pub direction: SortDirection,
+}
+
+/// Full retrieval request: which tasks, in what order, and how many.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TaskQuery {
+    /// Condition tasks must satisfy. Omit to match every task.
+    #[serde(default)]
+    pub filter: Option<TaskFilter>,
+    /// Sort keys, most significant first. Omit to sort by due date, soonest first.
+    #[serde(default)]
+    pub sort: Vec<TaskSort>,
+    /// Maximum number of tasks to return.
+    #[serde(default)]
+    pub limit: Option<u32>,
+    /// Also return soft-deleted tasks. Only set this when the user explicitly asks about deleted
+    /// tasks.
+    #[serde(default)]
+    pub include_deleted: bool,
+}
+
+impl TaskFilter {
+    /// Open or in progress.
+    pub fn unfinished() -> Self {
+        TaskFilter::Or {
+            filters: vec![
+                TaskFilter::Status {
+                    status: TaskStatus::Open,
+                },
+                TaskFilter::Status {
+                    status: TaskStatus::InProgress,
+                },
+            ],
+        }
+    }
+
+    /// Evaluates the filter against a single task, for backends that cannot compile it into a
+    /// query. Must agree with the SQL compilation in `db::task`.
+    pub fn matches(&self, task: &Task) -> bool {
+        match self {
+            TaskFilter::AssigneeEquals { assignee } => {
+                assignee.to_lowercase() == task.assignee.to_lowercase()
+            }
+            TaskFilter::AssigneeIn { assignees } => assignees
+                .iter()
+                .any(|assignee| assignee.to_lowercase() == task.assignee.to_lowercase()),
+            TaskFilter::DueBefore { before } => task.due_date < *before,
+            TaskFilter::DueAfter { after } => task.due_date >= *after,
+            TaskFilter::DueBetween { start, end } => {
+                task.due_date >= *start && task.due_date < *end
+            }
+            TaskFilter::CreatedBefore { before } => task.create_date < *before,
+            TaskFilter::CreatedAfter { after } => task.create_date >= *after,
+            TaskFilter::CreatedBetween { start, end } => {
+                task.create_date >= *start && task.create_date < *end
+            }
+            TaskFilter::CompletedBetween { start, end } => task
+                .completed_at
+                .is_some_and(|completed_at| completed_at >= *start && completed_at < *end),
+            TaskFilter::DescriptionContains { text } => task
+                .description
+                .to_lowercase()
+                .contains(&text.to_lowercase()),
+            TaskFilter::Status { status } => task.status == *status,
+            TaskFilter::And { filters } => filters.iter().all(|filter| filter.matches(task)),
+            TaskFilter::Or { filters } => filters.iter().any(|filter| filter.matches(task)),
+            TaskFilter::Not { filter } => !filter.matches(task),
+        }
+    }
+}
+
+impl TaskQuery {
+    pub fn effective_limit(&self) -> u32 {
+        self.limit
+            .map_or(MAX_QUERY_LIMIT, |limit| limit.min(MAX_QUERY_LIMIT))
+    }
+
+    /// Whether `task` belongs in the result set, including the soft-delete rule.
+    pub fn matches(&self, task: &Task) -> bool {
+        (self.include_deleted || task.deleted_at.is_none())
+            && self
+                .filter
+                .as_ref()
+                .map_or(true, |filter| filter.matches(task))
+    }
+
+    /// Result ordering: the requested sort keys, then due date and id as tie-breakers, matching
+    /// the `ORDER BY` the SQL backends emit. Text is compared case-insensitively, like the filters.
+    pub fn compare(&self, a: &Task, b: &Task) -> Ordering {
+        self.sort
+            .iter()
+            .map(|sort| {
+                let ordering = match sort.field {
+                    SortField::DueDate => a.due_date.cmp(&b.due_date),
+                    SortField::CreateDate => a.create_date.cmp(&b.create_date),
+                    SortField::Assignee => {
+                        a.assignee.to_lowercase().cmp(&b.assignee.to_lowercase())
+                    }
+                    SortField::Description => a
+                        .description
+                        .to_lowercase()
+                        .cmp(&b.description.to_lowercase()),
+                };
+                match sort.direction {
+                    SortDirection::Ascending => ordering,
+                    SortDirection::Descending => ordering.reverse(),
+                }
+            })
+            .chain([a.due_date.cmp(&b.due_date), a.id.cmp(&b.id)])
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
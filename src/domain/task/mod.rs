@@ -1,2 +1,5 @@
+pub mod event;
+pub mod filter;
 pub mod model;
+pub mod resolution;
 pub mod service;
@@ -1,7 +1,10 @@
 use std::fmt::{self, Display};
 
+use std::str::FromStr;
+
 use chrono::{DateTime, Utc};
 use partial_derive::Partial;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -13,19 +16,67 @@ pub struct Task {
     pub create_date: DateTime<Utc>,
     pub due_date: DateTime<Utc>,
     pub assignee: String,
+    pub status: TaskStatus,
+    /// Set when the task moves to `Done`, cleared if it is reopened.
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Set when the task is soft-deleted. Deleted tasks are hidden from queries unless asked for.
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 impl Display for Task {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Task: {} || Assignee: {} || Due Date: {}",
-            self.description, self.assignee, self.due_date
+            "Task: {} || Assignee: {} || Due Date: {} || Status: {}",
+            self.description, self.assignee, self.due_date, self.status
         )
     }
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    #[default]
+    Open,
+    InProgress,
+    Done,
+    Cancelled,
+}
+impl TaskStatus {
+    /// Representation used in storage, matching the `status` column check constraint.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskStatus::Open => "open",
+            TaskStatus::InProgress => "in_progress",
+            TaskStatus::Done => "done",
+            TaskStatus::Cancelled => "cancelled",
+        }
+    }
+}
+impl Display for TaskStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+impl FromStr for TaskStatus {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(TaskStatus::Open),
+            "in_progress" => Ok(TaskStatus::InProgress),
+            "done" => Ok(TaskStatus::Done),
+            "cancelled" => Ok(TaskStatus::Cancelled),
+            other => anyhow::bail!("Unknown task status: {}", other),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
 pub struct DisplayableTaskVec(Vec<Task>);
+impl DisplayableTaskVec {
+    pub fn tasks(&self) -> &[Task] {
+        &self.0
+    }
+}
 
 impl IntoIterator for DisplayableTaskVec {
     type Item = Task;
@@ -36,15 +87,14 @@ impl IntoIterator for DisplayableTaskVec {
         self.0.into_iter()
     }
 }
+/// Plain-text, one task per line. Chat clients should prefer `output::telegram::render_task_list`.
 impl Display for DisplayableTaskVec {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // FIXME
-        let mut iter = self.clone().into_iter();
-        if let Some(first) = iter.next() {
-            write!(f, "{}", first)?;
-            for task in iter {
-                write!(f, ", {}", task)?;
+        for (i, task) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
             }
+            write!(f, "{}. {}", i + 1, task)?;
         }
         Ok(())
     }
@@ -55,27 +105,6 @@ impl From<Vec<Task>> for DisplayableTaskVec {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct TaskFieldFilter {
-    pub field: TaskField,
-    pub sql_query: CheckedSqlQuery,
-}
-impl Default for TaskFieldFilter {
-    fn default() -> Self {
-        Self {
-            field: TaskField::Id,
-            sql_query: CheckedSqlQuery(String::from("")),
-        }
-    }
-}
-
-// NOTE: this is distinct from `&str` because it denotes that some SQL validation step has been
-// cleared prior to being passed to the database
-// FIXME: due consideration to be given to stopping LLM from generating security-compromising
-// queries, as well as queries doing anything that isn't reading
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct CheckedSqlQuery(pub String);
-
 pub enum ParamTransformErr {
     /// You are trying to convert Params into a Model where you shouldn't be (e.g. Delete)
     WrongAccessContext,
@@ -98,6 +127,9 @@ impl TryFrom<Extraction> for PartialTask {
                 create_date: Some(Utc::now()),
                 due_date,
                 assignee,
+                status: Some(TaskStatus::Open),
+                completed_at: None,
+                deleted_at: None,
             }),
             _ => Err(Self::Error::WrongAccessContext),
         }
@@ -0,0 +1,307 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+
+use super::model::{PartialTask, Task};
+
+/// How many candidates to offer the user when the reference is ambiguous.
+pub const MAX_CANDIDATES: usize = 3;
+
+const ASSIGNEE_WEIGHT: f64 = 0.3;
+const DESCRIPTION_WEIGHT: f64 = 0.5;
+const DUE_DATE_WEIGHT: f64 = 0.2;
+/// Minimum score for a candidate to be considered at all.
+const MATCH_THRESHOLD: f64 = 0.25;
+/// Minimum score for the best candidate to be picked without asking.
+const CONFIDENT_THRESHOLD: f64 = 0.6;
+/// Minimum lead the best candidate needs over the runner-up to be picked without asking.
+const CONFIDENT_MARGIN: f64 = 0.2;
+
+/// Outcome of matching a natural-language task reference against stored tasks.
+#[derive(Debug, Clone)]
+pub enum Resolution {
+    /// One task matched with high confidence.
+    Resolved(Task),
+    /// Several tasks are plausible; the user should choose. Ordered best first.
+    Ambiguous(Vec<Task>),
+    /// Nothing resembled the reference.
+    NoMatch,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScoredCandidate {
+    pub task: Task,
+    /// Weighted similarity in [0, 1] over the hints that were actually supplied.
+    pub score: f64,
+}
+
+/// Scores every candidate against the hints (assignee, due date, description) and returns them
+/// best first. Hints that were not supplied do not count towards or against any candidate.
+pub fn rank(hints: &PartialTask, candidates: Vec<Task>) -> Vec<ScoredCandidate> {
+    let hint_tokens = hints.description.as_deref().map(tokens);
+    let mut scored: Vec<ScoredCandidate> = candidates
+        .into_iter()
+        .map(|task| {
+            let mut total = 0.0;
+            let mut weights = 0.0;
+            if let Some(assignee) = &hints.assignee {
+                weights += ASSIGNEE_WEIGHT;
+                if assignee.trim().eq_ignore_ascii_case(task.assignee.trim()) {
+                    total += ASSIGNEE_WEIGHT;
+                }
+            }
+            if let (Some(hint_tokens), Some(description)) = (&hint_tokens, &hints.description) {
+                weights += DESCRIPTION_WEIGHT;
+                total += DESCRIPTION_WEIGHT
+                    * text_similarity(hint_tokens, description, &task.description);
+            }
+            if let Some(due_date) = hints.due_date {
+                weights += DUE_DATE_WEIGHT;
+                total += DUE_DATE_WEIGHT * due_date_similarity(due_date, task.due_date);
+            }
+            let score = if weights > 0.0 { total / weights } else { 0.0 };
+            ScoredCandidate { task, score }
+        })
+        .collect();
+    scored.sort_by(|a, b| b.score.total_cmp(&a.score));
+    scored
+}
+
+/// Picks the best candidate only if it is both a strong match and clearly ahead of the rest.
+pub fn decide(ranked: Vec<ScoredCandidate>) -> Resolution {
+    let plausible: Vec<ScoredCandidate> = ranked
+        .into_iter()
+        .filter(|candidate| candidate.score >= MATCH_THRESHOLD)
+        .collect();
+    let confident = match plausible.as_slice() {
+        [] => return Resolution::NoMatch,
+        [best] => best.score >= CONFIDENT_THRESHOLD,
+        [best, runner_up, ..] => {
+            best.score >= CONFIDENT_THRESHOLD && best.score - runner_up.score >= CONFIDENT_MARGIN
+        }
+    };
+    let mut plausible = plausible.into_iter().map(|candidate| candidate.task);
+    if confident {
+        return Resolution::Resolved(plausible.next().expect("non-empty checked above"));
+    }
+    Resolution::Ambiguous(plausible.take(MAX_CANDIDATES).collect())
+}
+
+pub fn resolve(hints: &PartialTask, candidates: Vec<Task>) -> Resolution {
+    decide(rank(hints, candidates))
+}
+
+/// Fraction of the reference's words found in the description, or a full match when the
+/// description contains the reference outright ("bins" vs "take the bins out").
+fn text_similarity(hint_tokens: &HashSet<String>, hint: &str, description: &str) -> f64 {
+    let hint = hint.trim().to_lowercase();
+    let description_lower = description.to_lowercase();
+    if !hint.is_empty() && description_lower.contains(&hint) {
+        return 1.0;
+    }
+    let description_tokens = tokens(description);
+    if hint_tokens.is_empty() || description_tokens.is_empty() {
+        return 0.0;
+    }
+    let shared = hint_tokens.intersection(&description_tokens).count() as f64;
+    // Score against the reference's own length: "the bins" should fully match a long description
+    // that mentions bins, rather than being diluted by the description's extra words.
+    shared / hint_tokens.len() as f64
+}
+
+/// Decays from 1 on the same day to 0.5 a day apart and towards 0 beyond that.
+fn due_date_similarity(hint: DateTime<Utc>, due_date: DateTime<Utc>) -> f64 {
+    let days_apart = (hint - due_date).num_hours().abs() as f64 / 24.0;
+    1.0 / (1.0 + days_apart)
+}
+
+const STOPWORDS: &[&str] = &[
+    "a", "an", "the", "task", "tasks", "for", "to", "of", "and", "my", "our", "on", "in", "that",
+    "this", "one",
+];
+
+fn tokens(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .map(|token| token.to_lowercase())
+        .filter(|token| !token.is_empty() && !STOPWORDS.contains(&token.as_str()))
+        .map(|token| token.trim_end_matches('s').to_string())
+        .filter(|token| !token.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::task::model::TaskStatus;
+    use chrono::{Duration, TimeZone};
+    use uuid::Uuid;
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, day, 12, 0, 0).unwrap()
+    }
+
+    fn task(description: &str, assignee: &str, due_day: u32) -> Task {
+        Task {
+            id: Uuid::new_v4(),
+            description: description.to_string(),
+            create_date: at(1),
+            due_date: at(due_day),
+            assignee: assignee.to_string(),
+            status: TaskStatus::Open,
+            completed_at: None,
+            deleted_at: None,
+        }
+    }
+
+    fn scored(description: &str, score: f64) -> ScoredCandidate {
+        ScoredCandidate {
+            task: task(description, "Sam", 5),
+            score,
+        }
+    }
+
+    fn descriptions(resolution: &Resolution) -> Vec<&str> {
+        match resolution {
+            Resolution::Resolved(task) => vec![task.description.as_str()],
+            Resolution::Ambiguous(tasks) => tasks.iter().map(|t| t.description.as_str()).collect(),
+            Resolution::NoMatch => vec![],
+        }
+    }
+
+    #[test]
+    fn descriptions_match_by_containment_or_shared_words() {
+        let hint = tokens("bins");
+        assert_eq!(text_similarity(&hint, "bins", "Take the bins out"), 1.0);
+
+        let hint = tokens("water garden plants");
+        let similarity = text_similarity(&hint, "water garden plants", "Water the plants");
+        assert!((similarity - 2.0 / 3.0).abs() < 1e-9);
+
+        let hint = tokens("the");
+        assert_eq!(text_similarity(&hint, "the", "Mow lawn"), 0.0);
+    }
+
+    #[test]
+    fn due_dates_score_by_distance() {
+        assert_eq!(due_date_similarity(at(5), at(5)), 1.0);
+        assert_eq!(due_date_similarity(at(5), at(6)), 0.5);
+        assert_eq!(due_date_similarity(at(6), at(5)), 0.5);
+        assert!(due_date_similarity(at(5), at(5) + Duration::days(9)) < 0.15);
+    }
+
+    #[test]
+    fn only_supplied_hints_count() {
+        let hints = PartialTask {
+            assignee: Some(String::from(" sam ")),
+            ..Default::default()
+        };
+
+        let ranked = rank(
+            &hints,
+            vec![
+                task("Mow the lawn", "Alex", 3),
+                task("Take the bins out", "Sam", 9),
+            ],
+        );
+
+        assert_eq!(ranked[0].task.description, "Take the bins out");
+        assert_eq!(ranked[0].score, 1.0);
+        assert_eq!(ranked[1].score, 0.0);
+        assert!(rank(
+            &PartialTask::default(),
+            vec![task("Mow the lawn", "Alex", 3)]
+        )
+        .iter()
+        .all(|candidate| candidate.score == 0.0));
+    }
+
+    #[test]
+    fn nothing_above_the_match_threshold_is_no_match() {
+        let resolution = decide(vec![scored("Mow the lawn", MATCH_THRESHOLD - 0.01)]);
+
+        assert!(matches!(resolution, Resolution::NoMatch));
+        assert!(matches!(decide(vec![]), Resolution::NoMatch));
+    }
+
+    #[test]
+    fn a_single_confident_candidate_is_resolved() {
+        let resolution = decide(vec![
+            scored("Take the bins out", CONFIDENT_THRESHOLD),
+            scored("Mow the lawn", MATCH_THRESHOLD - 0.01),
+        ]);
+
+        assert!(matches!(resolution, Resolution::Resolved(_)));
+        assert_eq!(descriptions(&resolution), vec!["Take the bins out"]);
+    }
+
+    #[test]
+    fn a_weak_best_candidate_is_ambiguous() {
+        let resolution = decide(vec![scored(
+            "Take the bins out",
+            CONFIDENT_THRESHOLD - 0.01,
+        )]);
+
+        assert!(matches!(resolution, Resolution::Ambiguous(_)));
+        assert_eq!(descriptions(&resolution), vec!["Take the bins out"]);
+    }
+
+    #[test]
+    fn a_close_runner_up_makes_it_ambiguous() {
+        let resolution = decide(vec![
+            scored("Take the bins out", 0.9),
+            scored("Take the recycling out", 0.9 - CONFIDENT_MARGIN + 0.01),
+            scored("Mow the lawn", 0.5),
+            scored("Water the plants", 0.4),
+            scored("Wash the car", 0.1),
+        ]);
+
+        assert!(matches!(resolution, Resolution::Ambiguous(_)));
+        assert_eq!(
+            descriptions(&resolution),
+            vec![
+                "Take the bins out",
+                "Take the recycling out",
+                "Mow the lawn"
+            ]
+        );
+    }
+
+    #[test]
+    fn a_clear_lead_is_resolved() {
+        let resolution = decide(vec![
+            scored("Take the bins out", 0.9),
+            scored("Take the recycling out", 0.9 - CONFIDENT_MARGIN - 0.01),
+        ]);
+
+        assert!(matches!(resolution, Resolution::Resolved(_)));
+        assert_eq!(descriptions(&resolution), vec!["Take the bins out"]);
+    }
+
+    #[test]
+    fn resolves_a_reference_against_stored_tasks() {
+        let candidates = vec![
+            task("Take the bins out", "Sam", 5),
+            task("Water the plants", "Alex", 5),
+            task("Mow the lawn", "Sam", 6),
+        ];
+        let hints = PartialTask {
+            description: Some(String::from("the bins")),
+            assignee: Some(String::from("Sam")),
+            ..Default::default()
+        };
+
+        let resolution = resolve(&hints, candidates.clone());
+        assert_eq!(descriptions(&resolution), vec!["Take the bins out"]);
+        assert!(matches!(resolution, Resolution::Resolved(_)));
+
+        let hints = PartialTask {
+            assignee: Some(String::from("Sam")),
+            ..Default::default()
+        };
+        let resolution = resolve(&hints, candidates);
+        assert!(matches!(resolution, Resolution::Ambiguous(_)));
+        assert_eq!(
+            descriptions(&resolution),
+            vec!["Take the bins out", "Mow the lawn"]
+        );
+    }
+}
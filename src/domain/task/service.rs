@@ -1,13 +1,19 @@
-use super::model::{PartialTask, Task, TaskFieldFilter};
-use crate::db::interface::Repository;
+use super::event::{ChangeOrigin, TaskEvent, TaskEventQuery};
+use super::filter::{SortDirection, SortField, TaskFilter, TaskQuery, TaskSort, MAX_QUERY_LIMIT};
+use super::model::{PartialTask, Task, TaskStatus};
+use super::resolution::{self, Resolution};
+use crate::db::interface::{EventLog, Repository};
 use anyhow::bail;
 use async_trait::async_trait;
+use chrono::Utc;
+use serde::de::StdError;
+use std::fmt::{Display, Formatter};
 use uuid::Uuid;
 
 pub struct Service<R: Repository<Task>> {
     repo: R,
 }
-impl<R: Repository<Task>> Service<R> {
+impl<R: Repository<Task, Filter = TaskQuery>> Service<R> {
     pub fn new(repo: R) -> Self {
         Self { repo }
     }
@@ -17,44 +23,369 @@ impl<R: Repository<Task>> Service<R> {
 }
 
 #[async_trait]
-impl<R: Repository<Task> + Sync> TaskDataFlows for Service<R> {
-    async fn create_new_task(&self, mut fields: PartialTask) -> anyhow::Result<Task> {
+impl<R> TaskDataFlows for Service<R>
+where
+    R: Repository<Task, Filter = TaskQuery>
+        + EventLog<Task, TaskEvent, EventFilter = TaskEventQuery>
+        + Sync,
+{
+    async fn create_new_task(
+        &self,
+        mut fields: PartialTask,
+        origin: &ChangeOrigin,
+    ) -> anyhow::Result<Task> {
         fields.id = Some(Uuid::new_v4());
+        fields.status = Some(TaskStatus::Open);
+        fields.completed_at = Some(None);
+        fields.deleted_at = Some(None);
         let new_task = Task::try_from(fields)?;
-        self.repo.save(new_task).await
+        let event = TaskEvent::new(origin, None, new_task.clone());
+        self.repo.save_with_event(new_task, event).await
     }
 
-    async fn modify_existing_task(&self, fields: PartialTask) -> anyhow::Result<Task> {
-        // TODO: Need to think about how to look up existing task -- current approach of looking up by uuid isn't feasible
+    async fn modify_existing_task(
+        &self,
+        mut fields: PartialTask,
+        origin: &ChangeOrigin,
+    ) -> anyhow::Result<Task> {
         if let None = fields.id {
             bail!("No task id specified for modification operation")
         }
-        let existing_task = self
-            .retrieve_task_by_id(fields.id.expect("task id presence already checked"))
-            .await?;
-        self.repo.save(fields.apply_partial(existing_task)).await
+        // These follow from creation, status changes and deletion, never from the caller.
+        fields.create_date = None;
+        fields.completed_at = None;
+        fields.deleted_at = None;
+        let id = fields.id.expect("task id presence already checked");
+        self.repo
+            .update_with_event(id, false, |existing_task| {
+                let was_done = existing_task.status == TaskStatus::Done;
+                let mut modified_task = fields.apply_partial(existing_task.clone());
+                match (was_done, modified_task.status == TaskStatus::Done) {
+                    (false, true) => modified_task.completed_at = Some(Utc::now()),
+                    (true, false) => modified_task.completed_at = None,
+                    _ => {}
+                }
+                let event = TaskEvent::new(origin, Some(existing_task), modified_task.clone());
+                Ok((modified_task, event))
+            })
+            .await
     }
 
-    async fn delete_existing_task(&self, fields: PartialTask) -> anyhow::Result<Task> {
-        // TODO: Need to think about how to look up existing task -- current approach of looking up by uuid isn't feasible
+    async fn delete_existing_task(
+        &self,
+        fields: PartialTask,
+        origin: &ChangeOrigin,
+    ) -> anyhow::Result<Task> {
         if let None = fields.id {
             bail!("No task id specified for delete operation")
         }
+        let id = fields.id.expect("task id presence already checked");
+        self.repo
+            .update_with_event(id, false, |existing_task| {
+                let mut deleted_task = existing_task.clone();
+                deleted_task.deleted_at = Some(Utc::now());
+                let event = TaskEvent::new(origin, Some(existing_task), deleted_task.clone());
+                Ok((deleted_task, event))
+            })
+            .await
+    }
+
+    async fn complete_task(
+        &self,
+        fields: PartialTask,
+        origin: &ChangeOrigin,
+    ) -> anyhow::Result<Task> {
+        if let None = fields.id {
+            bail!("No task id specified for completion operation")
+        }
+        let id = fields.id.expect("task id presence already checked");
+        self.repo
+            .update_with_event(id, false, |existing_task| {
+                let mut task = existing_task.clone();
+                task.status = TaskStatus::Done;
+                task.completed_at = Some(Utc::now());
+                let event = TaskEvent::new(origin, Some(existing_task), task.clone());
+                Ok((task, event))
+            })
+            .await
+    }
+
+    async fn retrieve_task(&self, id: Uuid) -> anyhow::Result<Task> {
+        self.retrieve_task_by_id(id).await
+    }
 
+    async fn retrieve_tasks(&self, query: TaskQuery) -> anyhow::Result<Vec<Task>> {
+        self.repo.retrieve_by_filter(query).await
+    }
+
+    async fn restore_task(
+        &self,
+        previous: Task,
+        expected: Task,
+        origin: &ChangeOrigin,
+    ) -> anyhow::Result<Task> {
         self.repo
-            .delete_by_id(fields.id.expect("task id presence already checked"))
+            .update_with_event(previous.id, true, |current| {
+                if !same_state(&current, &expected) {
+                    return Err(ChangedSinceErr { current }.into());
+                }
+                let event = TaskEvent::new(origin, Some(current), previous.clone());
+                Ok((previous, event))
+            })
             .await
     }
 
-    async fn retrieve_tasks(&self, filter: TaskFieldFilter) -> anyhow::Result<Vec<Task>> {
-        todo!()
+    async fn resolve_task(
+        &self,
+        hints: PartialTask,
+        include_deleted: bool,
+    ) -> anyhow::Result<Resolution> {
+        if let Some(id) = hints.id {
+            let task = if include_deleted {
+                self.repo.retrieve_by_id_including_deleted(id).await?
+            } else {
+                self.retrieve_task_by_id(id).await?
+            };
+            return Ok(Resolution::Resolved(task));
+        }
+        // Finished tasks pile up forever, so only unfinished ones are candidates for a change,
+        // and the most recently created are kept if even those exceed the limit. History can be
+        // asked about any task.
+        let candidates = self
+            .repo
+            .retrieve_by_filter(TaskQuery {
+                filter: (!include_deleted).then(TaskFilter::unfinished),
+                sort: vec![TaskSort {
+                    field: SortField::CreateDate,
+                    direction: SortDirection::Descending,
+                }],
+                limit: Some(MAX_QUERY_LIMIT),
+                include_deleted,
+            })
+            .await?;
+        Ok(resolution::resolve(&hints, candidates))
+    }
+
+    async fn retrieve_events(&self, query: TaskEventQuery) -> anyhow::Result<Vec<TaskEvent>> {
+        self.repo.retrieve_events(query).await
+    }
+}
+
+/// Whether two snapshots of a task agree on everything a user can see or change.
+fn same_state(a: &Task, b: &Task) -> bool {
+    a.id == b.id
+        && a.description == b.description
+        && a.due_date == b.due_date
+        && a.assignee == b.assignee
+        && a.status == b.status
+        && a.completed_at == b.completed_at
+        && a.deleted_at == b.deleted_at
+}
+
+/// A restore found the task changed since the snapshot it was meant to reverse.
+#[derive(Debug)]
+pub struct ChangedSinceErr {
+    /// The task as it stands, left untouched.
+    pub current: Task,
+}
+impl Display for ChangedSinceErr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "task {} has changed since", self.current.id)
     }
 }
+impl StdError for ChangedSinceErr {}
 
+/// Every mutating flow takes the `ChangeOrigin` it is recorded under in the task's history.
 #[async_trait]
 pub trait TaskDataFlows {
-    async fn create_new_task(&self, fields: PartialTask) -> anyhow::Result<Task>;
-    async fn modify_existing_task(&self, fields: PartialTask) -> anyhow::Result<Task>;
-    async fn delete_existing_task(&self, fields: PartialTask) -> anyhow::Result<Task>;
-    async fn retrieve_tasks(&self, filter: TaskFieldFilter) -> anyhow::Result<Vec<Task>>;
+    async fn create_new_task(
+        &self,
+        fields: PartialTask,
+        origin: &ChangeOrigin,
+    ) -> anyhow::Result<Task>;
+    /// Applies the given fields to the task. `create_date`, `completed_at` and `deleted_at` are
+    /// ignored: completion follows the status, and deletion has its own flow.
+    async fn modify_existing_task(
+        &self,
+        fields: PartialTask,
+        origin: &ChangeOrigin,
+    ) -> anyhow::Result<Task>;
+    /// Soft-deletes the task; it stays in storage but is hidden from retrievals.
+    async fn delete_existing_task(
+        &self,
+        fields: PartialTask,
+        origin: &ChangeOrigin,
+    ) -> anyhow::Result<Task>;
+    async fn complete_task(
+        &self,
+        fields: PartialTask,
+        origin: &ChangeOrigin,
+    ) -> anyhow::Result<Task>;
+    /// Fails with `RepositoryErr::NotFound` if the task doesn't exist or has been deleted.
+    async fn retrieve_task(&self, id: Uuid) -> anyhow::Result<Task>;
+    async fn retrieve_tasks(&self, query: TaskQuery) -> anyhow::Result<Vec<Task>>;
+    /// Writes back a full earlier snapshot of a task, undeleting it if it has since been deleted.
+    /// Fails with `ChangedSinceErr` unless the task is still exactly as `expected`, which is how
+    /// the operation being reversed left it, so later changes are never silently overwritten.
+    async fn restore_task(
+        &self,
+        previous: Task,
+        expected: Task,
+        origin: &ChangeOrigin,
+    ) -> anyhow::Result<Task>;
+    /// Matches a natural-language reference (description, assignee, due date) to a stored task.
+    /// Only open and in-progress tasks are candidates, unless `include_deleted` widens the search
+    /// to every task, finished or deleted.
+    async fn resolve_task(
+        &self,
+        hints: PartialTask,
+        include_deleted: bool,
+    ) -> anyhow::Result<Resolution>;
+    /// The change history, oldest first.
+    async fn retrieve_events(&self, query: TaskEventQuery) -> anyhow::Result<Vec<TaskEvent>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::task::memory;
+    use chrono::{DateTime, Duration, TimeZone};
+
+    fn task(description: &str, created: DateTime<Utc>, status: TaskStatus) -> Task {
+        Task {
+            id: Uuid::new_v4(),
+            description: description.to_string(),
+            create_date: created,
+            due_date: created + Duration::days(1),
+            assignee: String::from("Sam"),
+            status,
+            completed_at: None,
+            deleted_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn finished_tasks_do_not_crowd_out_open_ones() {
+        let repo = memory::Repository::new();
+        let start = Utc.with_ymd_and_hms(2024, 1, 7, 9, 0, 0).unwrap();
+        for week in 0..(MAX_QUERY_LIMIT + 20) {
+            let created = start + Duration::weeks(i64::from(week));
+            repo.save(task("Take the bins out", created, TaskStatus::Done))
+                .await
+                .unwrap();
+        }
+        let created = start + Duration::weeks(i64::from(MAX_QUERY_LIMIT + 20));
+        let open = task("Take the bins out", created, TaskStatus::Open);
+        repo.save(open.clone()).await.unwrap();
+        let service = Service::new(repo);
+        let hints = PartialTask {
+            description: Some(String::from("bins")),
+            ..Default::default()
+        };
+
+        let resolution = service.resolve_task(hints.clone(), false).await.unwrap();
+        assert!(matches!(resolution, Resolution::Resolved(task) if task.id == open.id));
+
+        let Resolution::Ambiguous(candidates) = service.resolve_task(hints, true).await.unwrap()
+        else {
+            panic!("every weekly bins task is a candidate for history");
+        };
+        assert_eq!(candidates[0].id, open.id);
+    }
+
+    fn origin(actor: &str) -> ChangeOrigin {
+        ChangeOrigin {
+            actor: actor.to_string(),
+            source_text: String::new(),
+            intent: String::new(),
+            params: serde_json::Value::Null,
+        }
+    }
+
+    async fn bins_task(service: &Service<memory::Repository>) -> Task {
+        service
+            .create_new_task(
+                PartialTask {
+                    description: Some(String::from("Take the bins out")),
+                    create_date: Some(Utc.with_ymd_and_hms(2026, 10, 18, 9, 0, 0).unwrap()),
+                    due_date: Some(Utc.with_ymd_and_hms(2026, 10, 19, 18, 0, 0).unwrap()),
+                    assignee: Some(String::from("Sam")),
+                    ..Default::default()
+                },
+                &origin("sam"),
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn restoring_refuses_to_overwrite_a_later_change() {
+        let service = Service::new(memory::Repository::new());
+        let created = bins_task(&service).await;
+        let reassigned = service
+            .modify_existing_task(
+                PartialTask {
+                    id: Some(created.id),
+                    assignee: Some(String::from("Alex")),
+                    ..Default::default()
+                },
+                &origin("sam"),
+            )
+            .await
+            .unwrap();
+        // Someone else finishes it before the reassignment is undone.
+        service
+            .complete_task(
+                PartialTask {
+                    id: Some(created.id),
+                    ..Default::default()
+                },
+                &origin("alex"),
+            )
+            .await
+            .unwrap();
+
+        let e = service
+            .restore_task(created.clone(), reassigned, &origin("sam"))
+            .await
+            .unwrap_err();
+
+        assert!(e.downcast_ref::<ChangedSinceErr>().is_some());
+        let current = service.retrieve_task(created.id).await.unwrap();
+        assert_eq!(current.status, TaskStatus::Done);
+        assert_eq!(current.assignee, "Alex");
+    }
+
+    #[tokio::test]
+    async fn restoring_an_untouched_task_undeletes_it() {
+        let service = Service::new(memory::Repository::new());
+        let created = bins_task(&service).await;
+        let deleted = service
+            .delete_existing_task(
+                PartialTask {
+                    id: Some(created.id),
+                    ..Default::default()
+                },
+                &origin("sam"),
+            )
+            .await
+            .unwrap();
+
+        service
+            .restore_task(created.clone(), deleted, &origin("sam"))
+            .await
+            .unwrap();
+
+        let restored = service.retrieve_task(created.id).await.unwrap();
+        assert!(restored.deleted_at.is_none());
+        let events = service
+            .retrieve_events(TaskEventQuery {
+                task_id: Some(created.id),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 3);
+        assert!(events[2].before.as_ref().unwrap().deleted_at.is_some());
+    }
 }
@@ -1,16 +1,63 @@
-use crate::domain::task::model::{DisplayableTaskVec, PartialTask, Task};
+pub mod undo;
+
+use crate::domain::task::event::{ChangeOrigin, DisplayableEventVec, TaskEventQuery};
+use crate::domain::task::model::{DisplayableTaskVec, PartialTask, Task, TaskStatus};
+use crate::domain::task::resolution::Resolution;
+use crate::domain::task::service::{ChangedSinceErr, TaskDataFlows};
 use crate::input::parsing_pipeline_steps::intent::Intent;
 use crate::input::parsing_pipeline_steps::params;
 use crate::telegram_bot;
-use chrono::Utc;
-use either::Either;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use serde::de::StdError;
+use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
+use undo::UndoOperation;
 
+/// Who asked for an operation, and in what words, for the task history.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Requester {
+    pub user: String,
+    /// The message text or voice transcript(s) the operation was parsed from.
+    pub source_text: String,
+}
+impl Requester {
+    fn origin(&self, intent: &Intent, params: &params::Extraction) -> ChangeOrigin {
+        ChangeOrigin {
+            actor: self.user.clone(),
+            source_text: self.source_text.clone(),
+            intent: intent.to_string(),
+            params: serde_json::to_value(params).unwrap_or_default(),
+        }
+    }
+}
+
+/// What an executed operation produced.
+#[derive(Serialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum Outcome {
+    Task(Task),
+    Tasks(DisplayableTaskVec),
+    History(DisplayableEventVec),
+}
+impl Display for Outcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Outcome::Task(task) => write!(f, "{}", task),
+            Outcome::Tasks(tasks) => write!(f, "{}", tasks),
+            Outcome::History(events) => write!(f, "{}", events),
+        }
+    }
+}
+
+#[derive(Serialize)]
 pub struct SuccessReport<T: Display> {
-    intent: Intent,
-    params: params::Extraction,
-    outcome: T,
+    pub intent: Intent,
+    pub params: params::Extraction,
+    pub outcome: T,
+    /// How to reverse the operation, if it changed anything.
+    #[serde(skip)]
+    pub undo: Option<UndoOperation>,
 }
 impl<T: Display> SuccessReport<T> {
     pub fn formatted_string(&self) -> String {
@@ -45,53 +92,167 @@ pub enum ExecutionErr {
     TaskRetrievalError {
         e: anyhow::Error,
     },
+    TaskCompletionError {
+        e: anyhow::Error,
+    },
+    TaskResolutionError {
+        e: anyhow::Error,
+    },
+    UndoError {
+        e: anyhow::Error,
+    },
+    /// The task has been changed since the operation being undone, so it was left as it is.
+    UndoConflict {
+        current: Task,
+    },
+    /// There is nothing left to undo in this conversation.
+    NothingToUndo,
+    /// Several stored tasks plausibly match the user's description; ask which one they meant and
+    /// retry with `params.with_target_id(..)`.
+    AmbiguousTaskReference {
+        intent: Intent,
+        params: params::Extraction,
+        candidates: Vec<Task>,
+    },
+    NoMatchingTask {
+        attempted_params: params::Extraction,
+    },
 }
 impl Display for ExecutionErr {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.to_string())
+        match self {
+            ExecutionErr::InvalidIntentParamPairing {
+                attempted_intent,
+                attempted_params,
+            } => write!(
+                f,
+                "intent {} cannot be executed with params {}",
+                attempted_intent, attempted_params
+            ),
+            ExecutionErr::TaskCreationError { e } => write!(f, "task creation failed: {}", e),
+            ExecutionErr::TaskModificationError { e } => {
+                write!(f, "task modification failed: {}", e)
+            }
+            ExecutionErr::TaskDeletionError { e } => write!(f, "task deletion failed: {}", e),
+            ExecutionErr::TaskRetrievalError { e } => write!(f, "task retrieval failed: {}", e),
+            ExecutionErr::TaskCompletionError { e } => write!(f, "task completion failed: {}", e),
+            ExecutionErr::TaskResolutionError { e } => write!(f, "task resolution failed: {}", e),
+            ExecutionErr::UndoError { e } => write!(f, "undo failed: {}", e),
+            ExecutionErr::UndoConflict { current } => {
+                write!(
+                    f,
+                    "task {} has changed since, so it was not undone",
+                    current.id
+                )
+            }
+            ExecutionErr::NothingToUndo => write!(f, "nothing to undo"),
+            ExecutionErr::AmbiguousTaskReference { candidates, .. } => {
+                write!(f, "{} tasks match the description", candidates.len())
+            }
+            ExecutionErr::NoMatchingTask { attempted_params } => {
+                write!(f, "no task matches {}", attempted_params)
+            }
+        }
     }
 }
 
 impl StdError for ExecutionErr {}
-pub async fn resolve<S: crate::domain::task::service::TaskDataFlows>(
+pub async fn resolve<S: TaskDataFlows>(
     intent: Intent,
     params: params::Extraction,
+    requester: &Requester,
     task_data_flows: &S,
-) -> Result<SuccessReport<Either<Task, DisplayableTaskVec>>> {
-    let outcome = match (intent.clone(), params.clone()) {
+) -> Result<SuccessReport<Outcome>> {
+    let origin = requester.origin(&intent, &params);
+    let (outcome, undo) = match (intent.clone(), params.clone()) {
         (Intent::CreateNewTask, params::Extraction::CreateNewTask { found }) => {
             let created_task = task_data_flows
-                .create_new_task(PartialTask {
-                    id: None,
-                    description: found.description,
-                    create_date: Some(Utc::now()),
-                    due_date: found.due_date,
-                    assignee: found.assignee,
-                })
+                .create_new_task(
+                    PartialTask {
+                        id: None,
+                        description: found.description,
+                        create_date: Some(Utc::now()),
+                        due_date: found.due_date,
+                        assignee: found.assignee,
+                        status: Some(TaskStatus::Open),
+                        completed_at: None,
+                        deleted_at: None,
+                    },
+                    &origin,
+                )
                 .await
                 .map_err(|e| ExecutionErr::TaskCreationError { e })?;
-            Ok(Either::Left(created_task))
+            Ok((
+                Outcome::Task(created_task.clone()),
+                Some(UndoOperation::DeleteCreated { task: created_task }),
+            ))
         }
         (Intent::ModifyExistingTask, params::Extraction::ModifyExistingTask { found }) => {
+            let previous = resolve_target(&intent, &params, found.target, task_data_flows).await?;
+            let mut changes = PartialTask::from(found.changes);
+            changes.id = Some(previous.id);
             let modified_task = task_data_flows
-                .modify_existing_task(found)
+                .modify_existing_task(changes, &origin)
                 .await
                 .map_err(|e| ExecutionErr::TaskModificationError { e })?;
-            Ok(Either::Left(modified_task))
+            Ok((
+                Outcome::Task(modified_task.clone()),
+                Some(UndoOperation::Restore {
+                    previous,
+                    applied: modified_task,
+                }),
+            ))
         }
-        (Intent::DeleteTask, params::Extraction::DeleteTask { found }) => {
+        (Intent::DeleteTask, params::Extraction::DeleteTask { mut found }) => {
+            let previous = resolve_target(&intent, &params, found.clone(), task_data_flows).await?;
+            found.id = Some(previous.id);
             let deleted_task = task_data_flows
-                .delete_existing_task(found)
+                .delete_existing_task(found, &origin)
                 .await
                 .map_err(|e| ExecutionErr::TaskDeletionError { e })?;
-            Ok(Either::Left(deleted_task))
+            Ok((
+                Outcome::Task(deleted_task.clone()),
+                Some(UndoOperation::Restore {
+                    previous,
+                    applied: deleted_task,
+                }),
+            ))
         }
         (Intent::QueryTasks, params::Extraction::QueryTasks { found }) => {
             let retrieved_tasks = task_data_flows
                 .retrieve_tasks(found)
                 .await
                 .map_err(|e| ExecutionErr::TaskRetrievalError { e })?;
-            Ok(Either::Right(DisplayableTaskVec::from(retrieved_tasks)))
+            Ok((
+                Outcome::Tasks(DisplayableTaskVec::from(retrieved_tasks)),
+                None,
+            ))
+        }
+        (Intent::CompleteTask, params::Extraction::CompleteTask { mut found }) => {
+            let previous = resolve_target(&intent, &params, found.clone(), task_data_flows).await?;
+            found.id = Some(previous.id);
+            let completed_task = task_data_flows
+                .complete_task(found, &origin)
+                .await
+                .map_err(|e| ExecutionErr::TaskCompletionError { e })?;
+            Ok((
+                Outcome::Task(completed_task.clone()),
+                Some(UndoOperation::Restore {
+                    previous,
+                    applied: completed_task,
+                }),
+            ))
+        }
+        (Intent::TaskHistory, params::Extraction::TaskHistory { found }) => {
+            let task = resolve_target(&intent, &params, found, task_data_flows).await?;
+            let events = task_data_flows
+                .retrieve_events(TaskEventQuery {
+                    task_id: Some(task.id),
+                    ..Default::default()
+                })
+                .await
+                .map_err(|e| ExecutionErr::TaskRetrievalError { e })?;
+            Ok((Outcome::History(DisplayableEventVec(events)), None))
         }
 
         (intent, mispaired_params) => Err(ExecutionErr::InvalidIntentParamPairing {
@@ -103,5 +264,188 @@ pub async fn resolve<S: crate::domain::task::service::TaskDataFlows>(
         intent,
         params,
         outcome,
+        undo,
+    })
+}
+
+/// Reverses the most recent recorded operation for `key` and reports the restored task.
+pub async fn undo_last<K: Eq + std::hash::Hash, S: TaskDataFlows>(
+    history: &undo::UndoHistory<K>,
+    key: &K,
+    requester: &Requester,
+    task_data_flows: &S,
+) -> Result<SuccessReport<Outcome>> {
+    let intent = Intent::Undo;
+    let params = params::Extraction::Undo {
+        found: params::Undo::default(),
+    };
+    let operation = history.pop(key).ok_or(ExecutionErr::NothingToUndo)?;
+    let restored = operation
+        .apply(task_data_flows, &requester.origin(&intent, &params))
+        .await
+        .map_err(|e| match e.downcast::<ChangedSinceErr>() {
+            Ok(changed) => ExecutionErr::UndoConflict {
+                current: changed.current,
+            },
+            Err(e) => ExecutionErr::UndoError { e },
+        })?;
+    Ok(SuccessReport {
+        intent,
+        params,
+        outcome: Outcome::Task(restored),
+        undo: None,
+    })
+}
+
+/// Turns the user's description of a task into the stored task, or explains why that isn't
+/// possible yet.
+async fn resolve_target<S: TaskDataFlows>(
+    intent: &Intent,
+    params: &params::Extraction,
+    hints: PartialTask,
+    task_data_flows: &S,
+) -> Result<Task> {
+    // Only a task's history is still worth looking up once it has been deleted.
+    let include_deleted = *intent == Intent::TaskHistory;
+    match task_data_flows
+        .resolve_task(hints, include_deleted)
+        .await
+        .map_err(|e| ExecutionErr::TaskResolutionError { e })?
+    {
+        Resolution::Resolved(task) => Ok(task),
+        Resolution::Ambiguous(candidates) => Err(ExecutionErr::AmbiguousTaskReference {
+            intent: intent.clone(),
+            params: params.clone(),
+            candidates,
+        }),
+        Resolution::NoMatch => Err(ExecutionErr::NoMatchingTask {
+            attempted_params: params.clone(),
+        }),
+    }
+}
+
+/// Which intents have to be confirmed by the user before they are executed.
+#[derive(Debug, Clone)]
+pub struct ConfirmationPolicy {
+    intents: Vec<Intent>,
+}
+impl Default for ConfirmationPolicy {
+    /// Anything that changes or removes an existing task.
+    fn default() -> Self {
+        Self {
+            intents: vec![Intent::ModifyExistingTask, Intent::DeleteTask],
+        }
+    }
+}
+impl ConfirmationPolicy {
+    pub fn new(intents: Vec<Intent>) -> Self {
+        Self { intents }
+    }
+
+    pub fn requires_confirmation(&self, intent: &Intent) -> bool {
+        self.intents.contains(intent)
+    }
+}
+
+/// An operation that has been resolved against storage but not yet executed. `params` already
+/// carries the target task's id, so executing it later cannot pick a different task.
+pub struct PendingOperation {
+    pub intent: Intent,
+    pub params: params::Extraction,
+    /// The task as it is now, for operations on an existing task.
+    pub current: Option<Task>,
+}
+impl PendingOperation {
+    /// What is about to happen, phrased as a question: the task to be deleted or completed, or a
+    /// field-by-field diff for a modification. Due dates are shown in `timezone`.
+    pub fn preview(&self, timezone: Tz) -> String {
+        let local = |due_date: DateTime<Utc>| {
+            due_date
+                .with_timezone(&timezone)
+                .format("%a %d %b %H:%M")
+                .to_string()
+        };
+        let line = |task: &Task| {
+            format!(
+                "Task: {} || Assignee: {} || Due Date: {} || Status: {}",
+                task.description,
+                task.assignee,
+                local(task.due_date),
+                task.status
+            )
+        };
+        match (&self.params, &self.current) {
+            (params::Extraction::ModifyExistingTask { found }, Some(current)) => {
+                let mut preview = format!("Change this task?\n{}\n", line(current));
+                let changes = &found.changes;
+                if let Some(description) = &changes.description {
+                    preview.push_str(&format!(
+                        "\nDescription: {} → {}",
+                        current.description, description
+                    ));
+                }
+                if let Some(assignee) = &changes.assignee {
+                    preview.push_str(&format!("\nAssignee: {} → {}", current.assignee, assignee));
+                }
+                if let Some(due_date) = &changes.due_date {
+                    preview.push_str(&format!(
+                        "\nDue Date: {} → {}",
+                        local(current.due_date),
+                        local(*due_date)
+                    ));
+                }
+                if let Some(status) = &changes.status {
+                    preview.push_str(&format!("\nStatus: {} → {}", current.status, status));
+                }
+                preview
+            }
+            (params::Extraction::DeleteTask { .. }, Some(current)) => {
+                format!("Delete this task?\n{}", line(current))
+            }
+            (params::Extraction::CompleteTask { .. }, Some(current)) => {
+                format!("Mark this task as done?\n{}", line(current))
+            }
+            (params::Extraction::CreateNewTask { found }, _) => format!(
+                "Create this task?\nTask: {} || Assignee: {} || Due Date: {}",
+                found.description.as_deref().unwrap_or_default(),
+                found.assignee.as_deref().unwrap_or_default(),
+                found.due_date.map(local).unwrap_or_default()
+            ),
+            (params, _) => format!(
+                "Go ahead with this?\n[Intent] {} || [Params] {}",
+                self.intent, params
+            ),
+        }
+    }
+}
+
+/// Resolves the task an operation targets without executing it, so the user can be shown what
+/// will change first. Fails the same way `resolve` would on ambiguous or unmatched references.
+pub async fn prepare<S: TaskDataFlows>(
+    intent: Intent,
+    params: params::Extraction,
+    task_data_flows: &S,
+) -> Result<PendingOperation> {
+    let hints = match &params {
+        params::Extraction::ModifyExistingTask { found } => Some(found.target.clone()),
+        params::Extraction::DeleteTask { found }
+        | params::Extraction::CompleteTask { found }
+        | params::Extraction::TaskHistory { found } => Some(found.clone()),
+        params::Extraction::CreateNewTask { .. }
+        | params::Extraction::QueryTasks { .. }
+        | params::Extraction::Undo { .. } => None,
+    };
+    let current = match hints {
+        Some(hints) => Some(resolve_target(&intent, &params, hints, task_data_flows).await?),
+        None => None,
+    };
+    let params = match &current {
+        Some(task) => params.with_target_id(task.id),
+        None => params,
+    };
+    Ok(PendingOperation {
+        intent,
+        params,
+        current,
     })
 }
@@ -0,0 +1,93 @@
+use crate::domain::task::event::ChangeOrigin;
+use crate::domain::task::model::{PartialTask, Task};
+use crate::domain::task::service::TaskDataFlows;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::hash::Hash;
+use std::sync::Mutex;
+
+/// How many operations each chat can step back through.
+pub const DEFAULT_UNDO_DEPTH: usize = 10;
+
+/// The inverse of a mutating execution, recorded when it succeeds.
+#[derive(Debug, Clone)]
+pub enum UndoOperation {
+    /// Undoes a creation by deleting the task that was created.
+    DeleteCreated { task: Task },
+    /// Undoes a modification, completion or deletion by writing back the task as it was before,
+    /// provided it is still as the operation left it (`applied`).
+    Restore { previous: Task, applied: Task },
+}
+impl Display for UndoOperation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UndoOperation::DeleteCreated { task } => write!(f, "remove the new task ({})", task),
+            UndoOperation::Restore { previous, .. } => write!(f, "restore {}", previous),
+        }
+    }
+}
+impl UndoOperation {
+    /// Applies the inverse and returns the task as it now stands.
+    pub async fn apply<S: TaskDataFlows>(
+        self,
+        task_data_flows: &S,
+        origin: &ChangeOrigin,
+    ) -> anyhow::Result<Task> {
+        match self {
+            UndoOperation::DeleteCreated { task } => {
+                task_data_flows
+                    .delete_existing_task(
+                        PartialTask {
+                            id: Some(task.id),
+                            ..Default::default()
+                        },
+                        origin,
+                    )
+                    .await
+            }
+            UndoOperation::Restore { previous, applied } => {
+                task_data_flows
+                    .restore_task(previous, applied, origin)
+                    .await
+            }
+        }
+    }
+}
+
+/// Per-conversation stacks of undoable operations, most recent last. Kept in memory: an undo
+/// only makes sense shortly after the operation, so losing the history on restart is acceptable.
+pub struct UndoHistory<K: Eq + Hash> {
+    depth: usize,
+    stacks: Mutex<HashMap<K, Vec<UndoOperation>>>,
+}
+impl<K: Eq + Hash> Default for UndoHistory<K> {
+    fn default() -> Self {
+        Self::new(DEFAULT_UNDO_DEPTH)
+    }
+}
+impl<K: Eq + Hash> UndoHistory<K> {
+    pub fn new(depth: usize) -> Self {
+        Self {
+            depth,
+            stacks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records an operation, dropping the oldest one once the stack is at its depth.
+    pub fn push(&self, key: K, operation: UndoOperation) {
+        let mut stacks = self.stacks.lock().expect("undo history lock poisoned");
+        let stack = stacks.entry(key).or_default();
+        stack.push(operation);
+        if stack.len() > self.depth {
+            stack.remove(0);
+        }
+    }
+
+    pub fn pop(&self, key: &K) -> Option<UndoOperation> {
+        self.stacks
+            .lock()
+            .expect("undo history lock poisoned")
+            .get_mut(key)
+            .and_then(Vec::pop)
+    }
+}
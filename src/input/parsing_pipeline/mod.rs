@@ -1,4 +1,5 @@
 use crate::llm::interface::LLMClient;
+use chrono_tz::Tz;
 
 use super::parsing_pipeline_steps::{
     intent::{self, Intent, IntentIdErr},
@@ -9,23 +10,27 @@ pub enum InputParseErr {
     ParamsErr(ExtractErr, Intent, Option<ExtractedParams>),
     OtherErr(anyhow::Error),
 }
+/// Identifies the intent with `intent_llm`, then extracts its parameters with `params_llm`.
 pub async fn from_text(
     text: &str,
-    llm_client: &impl LLMClient<String>,
+    intent_llm: &impl LLMClient<String>,
+    params_llm: &impl LLMClient<String>,
+    timezone: Tz,
 ) -> Result<(Intent, ExtractedParams), InputParseErr> {
-    let intent = intent::identify(llm_client, text)
+    let intent = intent::identify(intent_llm, text)
         .await
         .map_err(InputParseErr::IntentErr)?;
-    let params = params::extract(llm_client, &intent, text)
+    // Nothing to extract for an undo, so skip the second LLM round trip.
+    if let Intent::Undo = intent {
+        return Ok((
+            intent,
+            ExtractedParams::Undo {
+                found: params::Undo::default(),
+            },
+        ));
+    }
+    let params = params::extract(params_llm, &intent, text, timezone)
         .await
-        .map_err(|e| {
-            let clone = e.clone();
-            match e {
-                ExtractErr::Deserialization { .. } => {
-                    InputParseErr::ParamsErr(clone, intent.clone(), None)
-                }
-                ExtractErr::LLMFailed => InputParseErr::ParamsErr(clone, intent.clone(), None),
-            }
-        })?;
+        .map_err(|e| InputParseErr::ParamsErr(e, intent.clone(), None))?;
     Ok((intent, params))
 }
@@ -1,19 +1,21 @@
-use crate::domain::task::model::{CheckedSqlQuery, TaskField};
 use crate::input::parsing_pipeline_steps::params;
 use crate::llm::interface::LLMClient;
 use anyhow::Error;
-use schemars::schema::RootSchema;
-use schemars::schema_for_value;
+use schemars::schema::{RootSchema, Schema, SchemaObject};
+use schemars::{schema_for, schema_for_value};
 use serde::de::StdError;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum Intent {
     CreateNewTask,
     ModifyExistingTask,
     DeleteTask,
     QueryTasks,
+    CompleteTask,
+    Undo,
+    TaskHistory,
 }
 impl Display for Intent {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -30,18 +32,51 @@ impl Intent {
                 due_date: Default::default(),
                 assignee: "".to_string(),
             }),
-            Intent::ModifyExistingTask => schema_for_value!(params::ModifyExistingTask {
-                ..Default::default()
-            }),
-            Intent::DeleteTask => schema_for_value!(params::DeleteTask {
-                ..Default::default()
-            }),
-            Intent::QueryTasks => schema_for_value!(params::QueryTasks {
-                field: TaskField::Id,
-                sql_query: CheckedSqlQuery("".to_string())
-            }),
+            Intent::ModifyExistingTask => {
+                let mut schema = schema_for_value!(params::ModifyExistingTask {
+                    ..Default::default()
+                });
+                if let Some(Schema::Object(target)) = schema
+                    .schema
+                    .object
+                    .as_mut()
+                    .and_then(|object| object.properties.get_mut("target"))
+                {
+                    retain_properties(target, &params::TASK_HINT_FIELDS);
+                }
+                schema
+            }
+            // Only the fields a user would describe the task by.
+            Intent::DeleteTask | Intent::CompleteTask | Intent::TaskHistory => {
+                let mut schema = schema_for_value!(params::DeleteTask {
+                    ..Default::default()
+                });
+                retain_properties(&mut schema.schema, &params::TASK_HINT_FIELDS);
+                schema
+            }
+            Intent::QueryTasks => schema_for!(params::QueryTasks),
+            Intent::Undo => schema_for!(params::Undo),
         }
     }
+
+    /// The params schema narrowed to the named top-level properties, for follow-up questions
+    /// that should only fill in what is still missing.
+    pub fn get_params_schema_for(&self, fields: &[&str]) -> RootSchema {
+        let mut schema = self.get_params_schema();
+        retain_properties(&mut schema.schema, fields);
+        schema
+    }
+}
+
+fn retain_properties(schema: &mut SchemaObject, fields: &[&str]) {
+    if let Some(object) = schema.object.as_mut() {
+        object
+            .properties
+            .retain(|name, _| fields.contains(&name.as_str()));
+        object
+            .required
+            .retain(|name| fields.contains(&name.as_str()));
+    }
 }
 
 #[derive(Debug)]
@@ -52,7 +87,10 @@ pub enum IntentIdErr {
 
 impl Display for IntentIdErr {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.to_string())
+        match self {
+            IntentIdErr::NoApparentIntent => write!(f, "no apparent intent"),
+            IntentIdErr::LLMFailed => write!(f, "the LLM request failed"),
+        }
     }
 }
 
@@ -63,16 +101,32 @@ impl From<Error> for IntentIdErr {
     }
 }
 
+static IDENTIFY_SYSTEM_PROMPT: &str = r#"Classify what the user wants to do with their task list.
+Respond with a JSON object of the form {"intent": "<intent>"}, where <intent> is exactly one of:
+"create new task", "modify existing task", "delete task", "query tasks", "complete task" (the user says a task is done or finished), "undo" (the user wants to take back the last thing they did), "task history" (the user asks who changed a task, or what happened to it), or "no apparent intent"."#;
+
+#[derive(Deserialize)]
+struct IdentifiedIntent {
+    intent: String,
+}
+
 pub async fn identify(
     llm_client: &impl LLMClient<String>,
     text_message_content: &str,
 ) -> Result<Intent, IntentIdErr> {
-    let llm_response = llm_client.prompt(text_message_content).await?;
-    match llm_response.as_str() {
+    let llm_response = llm_client
+        .prompt_system_customized(text_message_content, IDENTIFY_SYSTEM_PROMPT)
+        .await?;
+    let identified: IdentifiedIntent =
+        serde_json::from_str(&llm_response).map_err(|_| IntentIdErr::LLMFailed)?;
+    match identified.intent.as_str() {
         "create new task" => Ok(Intent::CreateNewTask),
         "modify existing task" => Ok(Intent::ModifyExistingTask),
         "delete task" => Ok(Intent::DeleteTask),
         "query tasks" => Ok(Intent::QueryTasks),
+        "complete task" => Ok(Intent::CompleteTask),
+        "undo" => Ok(Intent::Undo),
+        "task history" => Ok(Intent::TaskHistory),
         "no apparent intent" => Err(IntentIdErr::NoApparentIntent),
         _ => Err(IntentIdErr::LLMFailed),
     }
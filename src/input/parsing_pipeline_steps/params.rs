@@ -1,5 +1,6 @@
 use super::intent::Intent;
-use crate::domain::task::model::{PartialTask, TaskFieldFilter};
+use crate::domain::task::filter::TaskQuery;
+use crate::domain::task::model::{PartialTask, TaskStatus};
 use anyhow::Error;
 use partial_derive::Partial;
 use serde::Deserialize;
@@ -8,7 +9,11 @@ use std::fmt::{Display, Formatter};
 
 use crate::llm::interface::LLMClient;
 use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use schemars::schema::RootSchema;
+use schemars::JsonSchema;
 use serde::de::StdError;
+use uuid::Uuid;
 
 #[derive(Partial, Default, Debug, Clone, Serialize, Deserialize)]
 pub struct CreateNewTask {
@@ -17,9 +22,75 @@ pub struct CreateNewTask {
     pub assignee: String,
 }
 
-pub type ModifyExistingTask = PartialTask;
+/// The fields a user may describe an existing task by. Only these are offered to the LLM when it
+/// has to pick out a task, since ids and lifecycle timestamps are never something a user says.
+pub const TASK_HINT_FIELDS: [&str; 3] = ["description", "due_date", "assignee"];
+
+/// A modification names the task being changed separately from the new values, since both can
+/// mention the same fields ("move Alex's bins task to Friday").
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModifyExistingTask {
+    /// How the user referred to the task to change.
+    pub target: PartialTask,
+    /// New values for the fields being changed. Leave everything else unset.
+    pub changes: TaskChanges,
+}
+impl ModifyExistingTask {
+    pub fn merge(self, other: Self, overwrite: bool) -> Self {
+        Self {
+            target: self.target.merge(other.target, overwrite),
+            changes: self.changes.merge(other.changes, overwrite),
+        }
+    }
+}
+
+/// What a modification may change. Creation and completion dates and deletion are managed by
+/// the service, so the LLM is never offered them.
+#[derive(Default, Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct TaskChanges {
+    pub description: Option<String>,
+    pub due_date: Option<DateTime<Utc>>,
+    pub assignee: Option<String>,
+    pub status: Option<TaskStatus>,
+}
+impl TaskChanges {
+    /// With `overwrite`, values set in `other` win; otherwise they only fill gaps.
+    pub fn merge(self, other: Self, overwrite: bool) -> Self {
+        fn pick<T>(mine: Option<T>, theirs: Option<T>, overwrite: bool) -> Option<T> {
+            if overwrite {
+                theirs.or(mine)
+            } else {
+                mine.or(theirs)
+            }
+        }
+        Self {
+            description: pick(self.description, other.description, overwrite),
+            due_date: pick(self.due_date, other.due_date, overwrite),
+            assignee: pick(self.assignee, other.assignee, overwrite),
+            status: pick(self.status, other.status, overwrite),
+        }
+    }
+}
+impl From<TaskChanges> for PartialTask {
+    fn from(changes: TaskChanges) -> Self {
+        PartialTask {
+            description: changes.description,
+            due_date: changes.due_date,
+            assignee: changes.assignee,
+            status: changes.status,
+            ..Default::default()
+        }
+    }
+}
 pub type DeleteTask = PartialTask;
-pub type QueryTasks = TaskFieldFilter;
+pub type QueryTasks = TaskQuery;
+pub type CompleteTask = PartialTask;
+pub type TaskHistory = PartialTask;
+/// Undo takes no params: it always reverses the most recent operation.
+#[derive(Default, Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Undo {}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 /// Note that `found` in each variant can be incomplete, since it is likely that the user misses
@@ -29,6 +100,164 @@ pub enum Extraction {
     ModifyExistingTask { found: ModifyExistingTask },
     DeleteTask { found: DeleteTask },
     QueryTasks { found: QueryTasks },
+    CompleteTask { found: CompleteTask },
+    Undo { found: Undo },
+    TaskHistory { found: TaskHistory },
+}
+
+impl Extraction {
+    /// Fills in the id of the task the operation applies to, once it has been resolved from the
+    /// user's description. Creations and queries have no target and are returned unchanged.
+    pub fn with_target_id(self, id: Uuid) -> Self {
+        match self {
+            Extraction::ModifyExistingTask { mut found } => {
+                found.target.id = Some(id);
+                Extraction::ModifyExistingTask { found }
+            }
+            Extraction::DeleteTask { mut found } => {
+                found.id = Some(id);
+                Extraction::DeleteTask { found }
+            }
+            Extraction::CompleteTask { mut found } => {
+                found.id = Some(id);
+                Extraction::CompleteTask { found }
+            }
+            Extraction::TaskHistory { mut found } => {
+                found.id = Some(id);
+                Extraction::TaskHistory { found }
+            }
+            other => other,
+        }
+    }
+
+    /// Names of the top-level params that still have to be asked for before this can be
+    /// executed. Empty when the extraction is complete. The names match the properties of
+    /// `Intent::get_params_schema`, so they can be used to narrow that schema for a follow-up.
+    pub fn missing_fields(&self) -> Vec<&'static str> {
+        match self {
+            Extraction::CreateNewTask { found } => match found.check_complete() {
+                Ok(_) => vec![],
+                Err(_) => [
+                    ("description", found.description.is_none()),
+                    ("due_date", found.due_date.is_none()),
+                    ("assignee", found.assignee.is_none()),
+                ]
+                .into_iter()
+                .filter_map(|(name, missing)| missing.then_some(name))
+                .collect(),
+            },
+            Extraction::ModifyExistingTask { found } => {
+                let mut missing = vec![];
+                if !identifies_task(&found.target) {
+                    missing.push("target");
+                }
+                if !has_changes(&found.changes) {
+                    missing.push("changes");
+                }
+                missing
+            }
+            // Any one hint is enough to go looking for the task; ask for all of them otherwise.
+            Extraction::DeleteTask { found }
+            | Extraction::CompleteTask { found }
+            | Extraction::TaskHistory { found } => {
+                if identifies_task(found) {
+                    vec![]
+                } else {
+                    TASK_HINT_FIELDS.to_vec()
+                }
+            }
+            // An empty query is valid: it lists everything.
+            Extraction::QueryTasks { .. } | Extraction::Undo { .. } => vec![],
+        }
+    }
+
+    /// Folds a follow-up answer into what was already extracted. Values from `additional` win,
+    /// except for queries, which are replaced wholesale since a filter can't be merged field-wise.
+    pub fn merge(self, additional: Self) -> Result<Self, ExtractErr> {
+        match (self, additional) {
+            (
+                Extraction::CreateNewTask { found: existing },
+                Extraction::CreateNewTask { found: additional },
+            ) => Ok(Extraction::CreateNewTask {
+                found: existing.merge(additional, true),
+            }),
+            (
+                Extraction::ModifyExistingTask { found: existing },
+                Extraction::ModifyExistingTask { found: additional },
+            ) => Ok(Extraction::ModifyExistingTask {
+                found: existing.merge(additional, true),
+            }),
+            (
+                Extraction::DeleteTask { found: existing },
+                Extraction::DeleteTask { found: additional },
+            ) => Ok(Extraction::DeleteTask {
+                found: existing.merge(additional, true),
+            }),
+            (Extraction::QueryTasks { .. }, Extraction::QueryTasks { found: additional }) => {
+                Ok(Extraction::QueryTasks { found: additional })
+            }
+            (
+                Extraction::CompleteTask { found: existing },
+                Extraction::CompleteTask { found: additional },
+            ) => Ok(Extraction::CompleteTask {
+                found: existing.merge(additional, true),
+            }),
+            (
+                Extraction::TaskHistory { found: existing },
+                Extraction::TaskHistory { found: additional },
+            ) => Ok(Extraction::TaskHistory {
+                found: existing.merge(additional, true),
+            }),
+            (Extraction::Undo { found }, Extraction::Undo { .. }) => Ok(Extraction::Undo { found }),
+            _ => Err(ExtractErr::MismatchedVariants),
+        }
+    }
+}
+
+fn identifies_task(hints: &PartialTask) -> bool {
+    hints.id.is_some()
+        || hints.description.is_some()
+        || hints.assignee.is_some()
+        || hints.due_date.is_some()
+}
+
+fn has_changes(changes: &TaskChanges) -> bool {
+    changes.description.is_some()
+        || changes.assignee.is_some()
+        || changes.due_date.is_some()
+        || changes.status.is_some()
+}
+
+/// A targeted follow-up asking only for what's missing.
+pub fn clarification_question(intent: &Intent, missing_fields: &[&str]) -> String {
+    let asks: Vec<&str> = missing_fields
+        .iter()
+        .map(|field| match (intent, *field) {
+            (Intent::DeleteTask | Intent::CompleteTask | Intent::TaskHistory, _) => {
+                "which task you mean"
+            }
+            (_, "description") => "what the task is",
+            (_, "due_date") => "when it's due",
+            (_, "assignee") => "who it's for",
+            (_, "target") => "which task you'd like to change",
+            (_, "changes") => "what you'd like to change about it",
+            (_, other) => other,
+        })
+        .fold(vec![], |mut asks, ask| {
+            if !asks.contains(&ask) {
+                asks.push(ask);
+            }
+            asks
+        });
+    let joined = match asks.as_slice() {
+        [] => String::new(),
+        [only] => only.to_string(),
+        [init @ .., last] => format!("{} and {}", init.join(", "), last),
+    };
+    format!(
+        "Almost there! Could you tell me {}? (Send /cancel to give up on this one.)",
+        joined
+    )
 }
 
 impl Default for Extraction {
@@ -40,7 +269,11 @@ impl Default for Extraction {
 }
 impl Display for Extraction {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.to_string())
+        write!(
+            f,
+            "{}",
+            serde_json::to_string(self).map_err(|_| std::fmt::Error)?
+        )
     }
 }
 
@@ -48,10 +281,18 @@ impl Display for Extraction {
 pub enum ExtractErr {
     Deserialization,
     LLMFailed,
+    /// A follow-up answer was parsed for a different intent than the one being clarified.
+    MismatchedVariants,
 }
 impl Display for ExtractErr {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.to_string())
+        match self {
+            ExtractErr::Deserialization => write!(f, "could not parse the extracted params"),
+            ExtractErr::LLMFailed => write!(f, "the LLM request failed"),
+            ExtractErr::MismatchedVariants => {
+                write!(f, "follow-up params do not match the original intent")
+            }
+        }
     }
 }
 impl StdError for ExtractErr {}
@@ -64,17 +305,89 @@ pub async fn extract(
     llm_client: &impl LLMClient<String>,
     intent: &Intent,
     text_message_content: &str,
+    timezone: Tz,
+) -> Result<Extraction, ExtractErr> {
+    extract_with_schema(
+        llm_client,
+        intent,
+        intent.get_params_schema(),
+        text_message_content,
+        timezone,
+    )
+    .await
+}
+
+/// Follow-up extraction for a clarification answer: the LLM only sees the schema for the fields
+/// that are still missing, plus the earlier messages for context.
+pub async fn extract_missing(
+    llm_client: &impl LLMClient<String>,
+    intent: &Intent,
+    missing_fields: &[&str],
+    input_log: &[String],
+    text_message_content: &str,
+    timezone: Tz,
+) -> Result<Extraction, ExtractErr> {
+    let prompt = format!(
+        "Earlier messages:\n{}\nAnswer to the follow-up question:\n{}",
+        input_log.join("\n"),
+        text_message_content
+    );
+    extract_with_schema(
+        llm_client,
+        intent,
+        intent.get_params_schema_for(missing_fields),
+        &prompt,
+        timezone,
+    )
+    .await
+}
+
+async fn extract_with_schema(
+    llm_client: &impl LLMClient<String>,
+    intent: &Intent,
+    schema: RootSchema,
+    text_message_content: &str,
+    timezone: Tz,
 ) -> Result<Extraction, ExtractErr> {
-    let schema = intent.get_params_schema();
     let generate_system_prompt = || -> String {
         let mut system_prompt = String::from("You will be provided text content to parse for input parameters, per the following schema:");
         system_prompt.push_str(serde_json::to_string_pretty(&schema).unwrap().as_str());
+        // Relative dates ("this week", "tomorrow") can only be resolved against the current time,
+        // and bare clock times ("6pm") only against the household's timezone.
+        system_prompt.push_str(
+            format!(
+                "\nThe current time is {} (timezone {}). Give dates with their UTC offset.",
+                Utc::now().with_timezone(&timezone).to_rfc3339(),
+                timezone.name()
+            )
+            .as_str(),
+        );
         system_prompt
     };
     let llm_response = llm_client
         .prompt_system_customized(text_message_content, generate_system_prompt().as_str())
         .await?;
-    let parse_result: serde_json::Result<Extraction> = serde_json::from_str(&llm_response);
+    // The LLM answers against the intent-specific schema, so deserialize into that shape and wrap.
+    let parse_result: serde_json::Result<Extraction> = match intent {
+        Intent::CreateNewTask => {
+            serde_json::from_str(&llm_response).map(|found| Extraction::CreateNewTask { found })
+        }
+        Intent::ModifyExistingTask => serde_json::from_str(&llm_response)
+            .map(|found| Extraction::ModifyExistingTask { found }),
+        Intent::DeleteTask => {
+            serde_json::from_str(&llm_response).map(|found| Extraction::DeleteTask { found })
+        }
+        Intent::QueryTasks => {
+            serde_json::from_str(&llm_response).map(|found| Extraction::QueryTasks { found })
+        }
+        Intent::CompleteTask => {
+            serde_json::from_str(&llm_response).map(|found| Extraction::CompleteTask { found })
+        }
+        Intent::Undo => serde_json::from_str(&llm_response).map(|found| Extraction::Undo { found }),
+        Intent::TaskHistory => {
+            serde_json::from_str(&llm_response).map(|found| Extraction::TaskHistory { found })
+        }
+    };
     match parse_result {
         Ok(params) => Ok(params),
         Err(_) => Err(ExtractErr::Deserialization),
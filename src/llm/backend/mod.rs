@@ -1 +1,60 @@
+pub mod ollama;
 pub mod openai;
+#[cfg(test)]
+pub mod scripted;
+
+use super::interface::LLMClient;
+use crate::config::{LlmBackend, LlmStageConfig};
+use async_trait::async_trait;
+
+/// Backend chosen at startup. Dispatches to the concrete client so the rest of the bot can stay
+/// generic over a single `LLMClient` type.
+pub enum AnyClient {
+    OpenAI(openai::Client),
+    Ollama(ollama::Client),
+}
+
+impl AnyClient {
+    /// Builds the client for one pipeline stage. Unset models and URLs fall back to each
+    /// backend's own default.
+    pub fn from_config(stage: &LlmStageConfig, system_prompt: Option<String>) -> Self {
+        match stage.backend.unwrap_or_default() {
+            LlmBackend::OpenAI => {
+                Self::OpenAI(openai::Client::new(stage.model.clone(), system_prompt))
+            }
+            LlmBackend::Ollama => Self::Ollama(
+                ollama::Client::new(stage.base_url.clone(), stage.model.clone(), system_prompt)
+                    .with_json_mode(stage.json_mode.unwrap_or(true)),
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl LLMClient<String> for AnyClient {
+    async fn prompt(&self, prompt: &str) -> anyhow::Result<String> {
+        match self {
+            AnyClient::OpenAI(client) => client.prompt(prompt).await,
+            AnyClient::Ollama(client) => client.prompt(prompt).await,
+        }
+    }
+
+    async fn prompt_system_customized(
+        &self,
+        prompt: &str,
+        customize_system_prompt: &str,
+    ) -> anyhow::Result<String> {
+        match self {
+            AnyClient::OpenAI(client) => {
+                client
+                    .prompt_system_customized(prompt, customize_system_prompt)
+                    .await
+            }
+            AnyClient::Ollama(client) => {
+                client
+                    .prompt_system_customized(prompt, customize_system_prompt)
+                    .await
+            }
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use crate::llm::interface;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+pub static DEFAULT_BASE_URL: &str = "http://localhost:11434";
+pub static DEFAULT_MODEL: &str = "llama3";
+static DEFAULT_SYSTEM_PROMPT: &str = "You are a helpful AI Assistant";
+
+/// Talks to an Ollama instance over its HTTP chat API (`POST /api/chat`).
+pub struct Client {
+    http_client: reqwest::Client,
+    base_url: String,
+    model: String,
+    system_prompt: String,
+    json_mode: bool,
+}
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessage<'a>>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+struct ChatMessage<'a> {
+    role: &'static str,
+    content: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    message: Option<ChatResponseMessage>,
+}
+
+#[derive(Deserialize)]
+struct ChatResponseMessage {
+    content: String,
+}
+
+#[async_trait]
+impl interface::LLMClient<String> for Client {
+    async fn prompt(&self, prompt: &str) -> anyhow::Result<String> {
+        self.prompt_inner(prompt, &self.system_prompt).await
+    }
+
+    async fn prompt_system_customized(
+        &self,
+        prompt: &str,
+        customize_system_prompt: &str,
+    ) -> anyhow::Result<String> {
+        let extended_system_prompt = format!("{}\n{}", customize_system_prompt, self.system_prompt);
+        self.prompt_inner(prompt, &extended_system_prompt).await
+    }
+}
+
+impl Client {
+    /// `base_url` is the Ollama server root, e.g. `http://localhost:11434`. JSON-format mode is on
+    /// by default, matching the OpenAI backend's `JsonObject` response format.
+    pub fn new(
+        base_url: Option<String>,
+        model: Option<String>,
+        system_prompt: Option<String>,
+    ) -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            base_url: base_url
+                .unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            model: model.unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+            system_prompt: system_prompt.unwrap_or_else(|| DEFAULT_SYSTEM_PROMPT.to_string()),
+            json_mode: true,
+        }
+    }
+
+    pub fn with_json_mode(mut self, json_mode: bool) -> Self {
+        self.json_mode = json_mode;
+        self
+    }
+
+    async fn prompt_inner(&self, prompt: &str, system_prompt: &str) -> anyhow::Result<String> {
+        let request = ChatRequest {
+            model: &self.model,
+            messages: vec![
+                ChatMessage {
+                    role: "system",
+                    content: system_prompt,
+                },
+                ChatMessage {
+                    role: "user",
+                    content: prompt,
+                },
+            ],
+            stream: false,
+            format: self.json_mode.then_some("json"),
+        };
+        let response: ChatResponse = self
+            .http_client
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let content = response
+            .message
+            .ok_or(interface::LLMError::NoChoicesGenerated)?
+            .content;
+        if content.trim().is_empty() {
+            return Err(interface::LLMError::EmptyResponse.into());
+        }
+        Ok(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::interface::{LLMClient, LLMError};
+    use axum::extract::State;
+    use axum::http::StatusCode;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use serde_json::{json, Value};
+    use std::sync::{Arc, Mutex};
+    use tokio::net::TcpListener;
+
+    /// Serves `/api/chat` on a local port, answering every request with `status` and `body` and
+    /// keeping the last request body for inspection.
+    async fn serve(status: StatusCode, body: Value) -> (String, Arc<Mutex<Option<Value>>>) {
+        let received = Arc::new(Mutex::new(None));
+        let router = Router::new()
+            .route(
+                "/api/chat",
+                post(
+                    |State(received): State<Arc<Mutex<Option<Value>>>>,
+                     Json(request): Json<Value>| async move {
+                        *received.lock().unwrap() = Some(request);
+                        (status, Json(body))
+                    },
+                ),
+            )
+            .with_state(received.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        (base_url, received)
+    }
+
+    fn client(base_url: String) -> Client {
+        Client::new(
+            Some(base_url),
+            Some(String::from("test-model")),
+            Some(String::from("Be brief.")),
+        )
+    }
+
+    #[tokio::test]
+    async fn sends_model_format_and_system_prompt() {
+        let (base_url, received) = serve(
+            StatusCode::OK,
+            json!({ "message": { "role": "assistant", "content": "{\"intent\": \"undo\"}" } }),
+        )
+        .await;
+
+        let response = client(base_url)
+            .prompt_system_customized("take that back", "Classify the request.")
+            .await
+            .unwrap();
+
+        assert_eq!(response, "{\"intent\": \"undo\"}");
+        let request = received.lock().unwrap().take().unwrap();
+        assert_eq!(request["model"], "test-model");
+        assert_eq!(request["format"], "json");
+        assert_eq!(request["stream"], false);
+        assert_eq!(
+            request["messages"],
+            json!([
+                { "role": "system", "content": "Classify the request.\nBe brief." },
+                { "role": "user", "content": "take that back" },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn leaves_format_out_without_json_mode() {
+        let (base_url, received) = serve(
+            StatusCode::OK,
+            json!({ "message": { "role": "assistant", "content": "Done!" } }),
+        )
+        .await;
+
+        client(base_url)
+            .with_json_mode(false)
+            .prompt("say done")
+            .await
+            .unwrap();
+
+        let request = received.lock().unwrap().take().unwrap();
+        assert!(request.get("format").is_none());
+    }
+
+    #[tokio::test]
+    async fn rejects_an_empty_response() {
+        let (base_url, _) = serve(
+            StatusCode::OK,
+            json!({ "message": { "role": "assistant", "content": "  " } }),
+        )
+        .await;
+
+        let e = client(base_url).prompt("anything").await.unwrap_err();
+
+        assert!(matches!(
+            e.downcast_ref::<LLMError>(),
+            Some(LLMError::EmptyResponse)
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_a_missing_message() {
+        let (base_url, _) = serve(StatusCode::OK, json!({ "done": true })).await;
+
+        let e = client(base_url).prompt("anything").await.unwrap_err();
+
+        assert!(matches!(
+            e.downcast_ref::<LLMError>(),
+            Some(LLMError::NoChoicesGenerated)
+        ));
+    }
+
+    #[tokio::test]
+    async fn surfaces_an_error_status() {
+        let (base_url, _) = serve(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            json!({ "error": "model not loaded" }),
+        )
+        .await;
+
+        let e = client(base_url).prompt("anything").await.unwrap_err();
+
+        let status = e
+            .downcast_ref::<reqwest::Error>()
+            .and_then(reqwest::Error::status);
+        assert_eq!(status.map(|status| status.as_u16()), Some(500));
+    }
+}
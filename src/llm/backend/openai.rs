@@ -10,12 +10,13 @@ use async_trait::async_trait;
 
 pub struct Client {
     inner_client: InnerClient<OpenAIConfig>,
+    model: String,
     system_prompt: String,
 }
 #[async_trait]
 impl interface::LLMClient<String> for Client {
     async fn prompt(&self, prompt: &str) -> anyhow::Result<String> {
-        Self::prompt_inner(prompt, &self.system_prompt, &self.inner_client).await
+        self.prompt_inner(prompt, &self.system_prompt).await
     }
 
     async fn prompt_system_customized(
@@ -24,29 +25,27 @@ impl interface::LLMClient<String> for Client {
         customize_system_prompt: &str,
     ) -> anyhow::Result<String> {
         let extended_system_prompt = format!("{}\n{}", customize_system_prompt, self.system_prompt);
-        Self::prompt_inner(prompt, &extended_system_prompt, &self.inner_client).await
+        self.prompt_inner(prompt, &extended_system_prompt).await
     }
 }
 
+pub static DEFAULT_MODEL: &str = "gpt-3.5-turbo";
 static DEFAULT_SYSTEM_PROMPT: &str = "You are a helpful AI Assistant";
 impl Client {
     /// Requires setting of OPENAI_API_KEY env var
-    pub fn new(system_prompt: Option<String>) -> Self {
+    pub fn new(model: Option<String>, system_prompt: Option<String>) -> Self {
         let inner_client = InnerClient::new();
         Self {
             inner_client,
+            model: model.unwrap_or_else(|| DEFAULT_MODEL.to_string()),
             system_prompt: system_prompt
                 .or(Some(DEFAULT_SYSTEM_PROMPT.to_string()))
                 .expect("use default system prompt if none provided"),
         }
     }
-    async fn prompt_inner(
-        prompt: &str,
-        system_prompt: &str,
-        client: &InnerClient<OpenAIConfig>,
-    ) -> anyhow::Result<String> {
+    async fn prompt_inner(&self, prompt: &str, system_prompt: &str) -> anyhow::Result<String> {
         let request = CreateChatCompletionRequestArgs::default()
-            .model("gpt-3.5-turbo")
+            .model(&self.model)
             .n(1)
             .response_format(ChatCompletionResponseFormat {
                 r#type: ChatCompletionResponseFormatType::JsonObject,
@@ -62,7 +61,7 @@ impl Client {
                     .into(),
             ])
             .build()?;
-        let chat_completion_response = client.chat().create(request).await?;
+        let chat_completion_response = self.inner_client.chat().create(request).await?;
         let completion_choice = chat_completion_response
             .choices
             .first()
@@ -0,0 +1,103 @@
+use crate::llm::interface;
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Deterministic stand-in for a real model: replays canned responses and records every prompt it
+/// receives, so whole conversations can run offline and be asserted on afterwards.
+pub struct Client {
+    script: Mutex<Script>,
+    recorded: Mutex<Vec<RecordedPrompt>>,
+}
+
+enum Script {
+    /// Responses are handed out in order, one per prompt, regardless of content.
+    Sequence(VecDeque<String>),
+    /// The first entry whose key occurs in the prompt (or the customized system prompt) answers.
+    Keyed(Vec<(String, String)>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedPrompt {
+    pub prompt: String,
+    /// The customization passed to `prompt_system_customized`, if that was the entry point.
+    pub customize_system_prompt: Option<String>,
+}
+
+#[async_trait]
+impl interface::LLMClient<String> for Client {
+    async fn prompt(&self, prompt: &str) -> anyhow::Result<String> {
+        self.respond(RecordedPrompt {
+            prompt: prompt.to_string(),
+            customize_system_prompt: None,
+        })
+    }
+
+    async fn prompt_system_customized(
+        &self,
+        prompt: &str,
+        customize_system_prompt: &str,
+    ) -> anyhow::Result<String> {
+        self.respond(RecordedPrompt {
+            prompt: prompt.to_string(),
+            customize_system_prompt: Some(customize_system_prompt.to_string()),
+        })
+    }
+}
+
+impl Client {
+    pub fn sequence<S: Into<String>>(responses: impl IntoIterator<Item = S>) -> Self {
+        Self::with_script(Script::Sequence(
+            responses.into_iter().map(Into::into).collect(),
+        ))
+    }
+
+    pub fn keyed<K: Into<String>, S: Into<String>>(
+        responses: impl IntoIterator<Item = (K, S)>,
+    ) -> Self {
+        Self::with_script(Script::Keyed(
+            responses
+                .into_iter()
+                .map(|(key, response)| (key.into(), response.into()))
+                .collect(),
+        ))
+    }
+
+    /// Every prompt received so far, oldest first.
+    pub fn recorded_prompts(&self) -> Vec<RecordedPrompt> {
+        self.recorded
+            .lock()
+            .expect("recording lock poisoned")
+            .clone()
+    }
+
+    fn with_script(script: Script) -> Self {
+        Self {
+            script: Mutex::new(script),
+            recorded: Mutex::new(vec![]),
+        }
+    }
+
+    fn respond(&self, received: RecordedPrompt) -> anyhow::Result<String> {
+        let response = match &mut *self.script.lock().expect("script lock poisoned") {
+            Script::Sequence(responses) => responses.pop_front(),
+            Script::Keyed(responses) => responses
+                .iter()
+                .find(|(key, _)| {
+                    received.prompt.contains(key.as_str())
+                        || received
+                            .customize_system_prompt
+                            .as_deref()
+                            .is_some_and(|system| system.contains(key.as_str()))
+                })
+                .map(|(_, response)| response.clone()),
+        };
+        let prompt = received.prompt.clone();
+        self.recorded
+            .lock()
+            .expect("recording lock poisoned")
+            .push(received);
+        response
+            .ok_or_else(|| anyhow::anyhow!("Scripted LLM has no response for prompt: {}", prompt))
+    }
+}
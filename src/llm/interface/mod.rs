@@ -20,7 +20,10 @@ pub enum LLMError {
 }
 impl Display for LLMError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.to_string())
+        match self {
+            LLMError::NoChoicesGenerated => write!(f, "LLM generated no choices"),
+            LLMError::EmptyResponse => write!(f, "LLM returned an empty response"),
+        }
     }
 }
 impl StdError for LLMError {}
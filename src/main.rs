@@ -1,24 +1,31 @@
 #![feature(associated_type_defaults)]
 #![feature(impl_trait_in_fn_trait_return)]
 
+mod api;
+mod auth;
+mod config;
+mod conversation;
 mod db;
+mod digest;
 mod domain;
 mod execution;
 mod input;
 mod llm;
+mod output;
+mod reminder;
 mod telegram_bot;
 mod transcription;
 
-use sqlx::postgres::PgPool;
-use sqlx::Postgres;
 use std::env;
 use std::sync::Arc;
 use telegram_bot::{Context, Describe};
-use teloxide::dispatching::dialogue::InMemStorage;
 use teloxide::dispatching::{Dispatcher, UpdateFilterExt};
 use teloxide::net::Download;
 use teloxide::prelude::*;
-use teloxide::types::{File as TelegramFile, MediaKind, Message, MessageKind};
+use teloxide::types::{
+    CallbackQuery, ChatId, File as TelegramFile, MediaKind, Message, MessageKind, ParseMode, UserId,
+};
+use teloxide::utils::command::BotCommands;
 use teloxide::{dptree, Bot};
 use tokio::fs::{self, File as TokioFile};
 use tokio::io::AsyncReadExt;
@@ -30,33 +37,125 @@ async fn main() -> anyhow::Result<()> {
     // initialize tracing
     tracing_subscriber::fmt::init();
 
-    let pool = PgPool::connect(&env::var("DATABASE_URL")?).await?;
-    let task_repo = db::task::Repository::new(pool);
-    let task_service = domain::task::service::Service::new(task_repo);
-    let llm_client = llm::backend::openai::Client::new(Some(String::from(
-        r#"You are managing a task assignment system. Provide all response as JSON objects only.
-In the event of errors or uncertain outcome, return the empty JSON object."#,
-    )));
-    let whisper_client = transcription::backend::openai::WhisperClient::new();
+    let config = config::Config::load()?;
+    config.validate_database()?;
+    let database = db::connect(&config.database.url).await?;
+    db::migrate::run(&database).await?;
+    if env::args().any(|arg| arg == "--migrate-only") {
+        log::info!("Migrations applied; exiting (--migrate-only)");
+        return Ok(());
+    }
+    let task_repo = db::task::AnyRepository::new(&database);
+    if env::args().any(|arg| arg == "--export-events") {
+        // One JSON object per line, oldest first, for audits outside the bot.
+        use db::interface::EventLog as _;
+        for event in task_repo
+            .retrieve_events(domain::task::event::TaskEventQuery::default())
+            .await?
+        {
+            println!("{}", serde_json::to_string(&event)?);
+        }
+        return Ok(());
+    }
+    // Everything past this point is only needed to run the bot itself.
+    config.validate()?;
+    let dialogue_storage = db::dialogue::Storage::new(
+        database.clone(),
+        chrono::Duration::hours(config.database.dialogue_ttl_hours),
+    );
+    tokio::spawn(dialogue_storage.clone().sweep_periodically());
+    if config.http.enabled {
+        // Clones of a repository share its connection pool (or in-memory store), so the API and
+        // the bot see the same tasks.
+        let interpreter = api::interpret::Interpreter {
+            engine: engine(&config, task_repo.clone()),
+            conversations: api::interpret::Conversations::new(chrono::Duration::hours(
+                config.database.dialogue_ttl_hours,
+            )),
+        };
+        let router = api::router(
+            Arc::new(domain::task::service::Service::new(task_repo.clone())),
+            Arc::new(interpreter),
+        );
+        let listener = tokio::net::TcpListener::bind(config.http.listen).await?;
+        log::info!("Serving the HTTP API on {}", config.http.listen);
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, router).await {
+                log::error!("HTTP API stopped: {}", e);
+            }
+        });
+    }
+    let reminders = Arc::new(db::reminder::Store::new(database.clone()));
+    let digests = Arc::new(db::digest::Store::new(database.clone()));
     let ctx = telegram_bot::Context {
-        transcription_client: whisper_client,
-        llm_client,
+        engine: engine(&config, task_repo.clone()),
+        listed_tasks: telegram_bot::ListedTasks::default(),
+        reminders: reminders.clone(),
+        digests: digests.clone(),
+        digest_defaults: config.digests.clone(),
         chat_log: vec![],
-        task_data_flows: task_service,
     };
 
-    let telegram_bot = teloxide::Bot::new(telegram_bot::TELEGRAM_BOT_API_KEY);
+    let gate = Arc::new(auth::Gate::new(
+        config.auth.passphrase.clone(),
+        config
+            .auth
+            .allowed_user_ids
+            .iter()
+            .copied()
+            .map(UserId)
+            .collect(),
+        db::member::Store::new(database.clone()),
+    ));
+    gate.sync_allow_list().await?;
+
+    let telegram_bot = teloxide::Bot::new(&config.telegram.bot_token);
+    telegram_bot
+        .set_my_commands(telegram_bot::Command::bot_commands())
+        .await?;
+    if config.digests.enabled {
+        let scheduler = digest::Scheduler {
+            task_data_flows: domain::task::service::Service::new(task_repo.clone()),
+            digests,
+            reminders: reminders.clone(),
+            poll_interval: std::time::Duration::from_secs(config.digests.poll_interval_secs),
+        };
+        tokio::spawn(scheduler.run(telegram_bot.clone()));
+    }
+    if config.reminders.enabled {
+        let scheduler = reminder::Scheduler {
+            task_data_flows: domain::task::service::Service::new(task_repo),
+            store: reminders,
+            offsets: config
+                .reminders
+                .offsets_minutes
+                .iter()
+                .map(|minutes| chrono::Duration::minutes(*minutes))
+                .collect(),
+            poll_interval: std::time::Duration::from_secs(config.reminders.poll_interval_secs),
+            timezone: config.timezone,
+        };
+        tokio::spawn(scheduler.run(telegram_bot.clone()));
+    }
     Dispatcher::builder(
         telegram_bot,
-        Update::filter_message()
-            .enter_dialogue::<Message, InMemStorage<telegram_bot::InteractionSteps>, telegram_bot::InteractionSteps>()
-            .branch(dptree::case![telegram_bot::InteractionSteps::ReceiveInput].endpoint(receive_input::<transcription::backend::openai::WhisperClient, llm::backend::openai::Client, domain::task::service::Service<db::task::Repository<Postgres>>>))
-            .branch(dptree::case![telegram_bot::InteractionSteps::ValidateParams { input_log, intent, params }].endpoint(validate_params::<transcription::backend::openai::WhisperClient, llm::backend::openai::Client, domain::task::service::Service<db::task::Repository<Postgres>>>))
+        dptree::entry()
+            .branch(
+                Update::filter_message()
+                    .filter_async(|bot: Bot, msg: Message, gate: Arc<auth::Gate>| async move { admit_message(&bot, &msg, &gate).await })
+                    .enter_dialogue::<Message, db::dialogue::Storage, conversation::InteractionSteps>()
+                    .branch(dptree::entry().filter_command::<telegram_bot::Command>().endpoint(handle_command::<transcription::backend::AnyTranscriptionClient, llm::backend::AnyClient, domain::task::service::Service<db::task::AnyRepository>>))
+                    .branch(dptree::endpoint(receive_message::<transcription::backend::AnyTranscriptionClient, llm::backend::AnyClient, domain::task::service::Service<db::task::AnyRepository>>)),
+            )
+            .branch(
+                Update::filter_callback_query()
+                    .filter_async(|bot: Bot, q: CallbackQuery, gate: Arc<auth::Gate>| async move { admit_callback(&bot, &q, &gate).await })
+                    .enter_dialogue::<CallbackQuery, db::dialogue::Storage, conversation::InteractionSteps>()
+                    .branch(dptree::filter(|q: CallbackQuery| q.data.as_deref().and_then(telegram_bot::TaskAction::parse_callback_data).is_some()).endpoint(task_action::<transcription::backend::AnyTranscriptionClient, llm::backend::AnyClient, domain::task::service::Service<db::task::AnyRepository>>))
+                    .branch(dptree::endpoint(press_button::<transcription::backend::AnyTranscriptionClient, llm::backend::AnyClient, domain::task::service::Service<db::task::AnyRepository>>)),
+            ),
     )
-    .dependencies(dptree::deps![
-        ctx,
-        InMemStorage::<telegram_bot::InteractionSteps>::new()
-    ])
+    .dependencies(dptree::deps![Arc::new(ctx), dialogue_storage, gate])
     .enable_ctrlc_handler()
     .build()
     .dispatch()
@@ -64,7 +163,88 @@ In the event of errors or uncertain outcome, return the empty JSON object."#,
     Ok(())
 }
 
-async fn receive_input<
+/// A conversation engine over the configured backends. The bot and the HTTP API each get their
+/// own, keyed by their own kind of conversation id.
+fn engine<K: Eq + std::hash::Hash>(
+    config: &config::Config,
+    task_repo: db::task::AnyRepository,
+) -> conversation::Engine<
+    transcription::backend::AnyTranscriptionClient,
+    llm::backend::AnyClient,
+    domain::task::service::Service<db::task::AnyRepository>,
+    K,
+> {
+    let llm_client = |stage| {
+        llm::backend::AnyClient::from_config(
+            &config.llm.stage(stage),
+            Some(config.llm.system_prompt.clone()),
+        )
+    };
+    conversation::Engine {
+        transcription_client: transcription::backend::AnyTranscriptionClient::from_config(
+            &config.transcription,
+        ),
+        intent_llm: llm_client(config::PipelineStage::Intent),
+        params_llm: llm_client(config::PipelineStage::Params),
+        reply_llm: llm_client(config::PipelineStage::Reply),
+        task_data_flows: domain::task::service::Service::new(task_repo),
+        confirmation_policy: config.confirmation.policy(),
+        reply_renderer: config.reply.renderer,
+        timezone: config.timezone,
+        undo_history: execution::undo::UndoHistory::default(),
+    }
+}
+
+/// Lets approved users' messages through to the dialogue. Anyone else is checked for the
+/// passphrase and otherwise refused once, before any LLM call is made.
+async fn admit_message(bot: &Bot, msg: &Message, gate: &auth::Gate) -> bool {
+    match gate.admit(msg.from(), msg.text()).await {
+        Ok(auth::Admission::Approved) => true,
+        Ok(auth::Admission::Unlocked) => {
+            let _ = bot
+                .send_message(msg.chat.id, "You're in! Send /help to see what I can do.")
+                .await;
+            false
+        }
+        Ok(auth::Admission::Refused { first_time }) => {
+            if first_time {
+                let _ = bot
+                    .send_message(
+                        msg.chat.id,
+                        "Sorry, I only work for approved members. Send the passphrase to get in.",
+                    )
+                    .await;
+            }
+            false
+        }
+        Err(e) => {
+            log::error!("Could not check whether the sender is approved: {}", e);
+            false
+        }
+    }
+}
+
+/// Button presses don't carry a passphrase, so they are only let through for approved users.
+async fn admit_callback(bot: &Bot, q: &CallbackQuery, gate: &auth::Gate) -> bool {
+    match gate.is_approved(Some(&q.from)).await {
+        Ok(true) => true,
+        Ok(false) => {
+            let _ = bot
+                .answer_callback_query(q.id.clone())
+                .text("Sorry, I only work for approved members.")
+                .await;
+            false
+        }
+        Err(e) => {
+            log::error!("Could not check whether the sender is approved: {}", e);
+            false
+        }
+    }
+}
+
+/// Text and voice messages that aren't commands. Beyond pulling the content out of the message,
+/// everything is up to the conversation engine.
+async fn receive_message<
     T: transcription::interface::TranscriptionClient,
     L: llm::interface::LLMClient<String>,
     S: domain::task::service::TaskDataFlows,
@@ -74,157 +254,170 @@ async fn receive_input<
     dialogue: telegram_bot::Dialogue,
     ctx: Arc<Context<T, L, S>>,
 ) -> anyhow::Result<()> {
-    bot.send_message(msg.chat.id, "Gotcha! One second...")
-        .await?;
-    let extracted_input = match &msg.kind {
-        MessageKind::Common(msg_common) => match &msg_common.media_kind {
-            MediaKind::Text(content) => Some(content.text.clone()),
-            MediaKind::Voice(content) => transcribe_voice_input(&bot, content, &msg, &ctx).await?,
-            other_media_kinds => {
-                bot.send_message(
-                    msg.chat.id,
-                    format!(
-                        "I don't know what to do with {} messages. Sorry!",
-                        other_media_kinds.describe()
-                    ),
-                )
-                .await?;
-                None
-            }
-        },
-        _ => None,
+    let step = dialogue.get().await?.unwrap_or_default();
+    let MessageKind::Common(msg_common) = &msg.kind else {
+        return Ok(());
     };
-    Ok(if let Some(content) = extracted_input {
-        match input::parsing_pipeline::from_text(&content, &ctx.llm_client).await {
-            Ok((intent, params)) => {
-                dispatch(&bot, &msg, dialogue, ctx, intent, params).await;
-            }
-            Err(e) => {
-                match e {
-                    // NOTE: Defensive / Corrective routing should happen here
-                    input::parsing_pipeline::InputParseErr::IntentErr(intent_err) => {
-                        bot.send_message(
-                            msg.chat.id,
-                            format!(
-                                r#"I didn't really understand what you were looking for there.
-                                Error: {}"#,
-                                intent_err
-                            ),
-                        )
-                        .await;
-                        // TODO: retry intent extraction prompt?
-                    }
-                    input::parsing_pipeline::InputParseErr::ParamsErr(
-                        pe,
-                        intent,
-                        extraction_attempt,
-                    ) => {
-                        bot.send_message(
-                            msg.chat.id,
-                            format!(
-                                r#"I think I got what you were looking for, but I'm not sure you gave me everything I needed to what you're asking.
-                                Error: {}
-                                Intent: {}
-                                Params Found: {:#?}
-                                "#,
-                                pe,
-                                intent,
-                                extraction_attempt
-                            ),
-                        ).await;
-                        // TODO: based on missing params, ask the user to provide in a subsequent
-                        // message, and first generate schema based on missing fields and then run
-                        // transcription / extraction against that
-                        dialogue
-                            .update(telegram_bot::InteractionSteps::ValidateParams {
-                                input_log: vec![content],
-                                intent,
-                                params: extraction_attempt,
-                            })
-                            .await;
-                    }
-                    input::parsing_pipeline::InputParseErr::OtherErr(oe) => {
-                        bot.send_message(
-                            msg.chat.id,
-                            format!(
-                                r#"Something went wrong there... Could you try providing an input again?
-                                Error: {}"#,
-                                oe
-                            ),
-                        )
-                        .await;
-                    }
-                }
-            }
+    if let conversation::InteractionSteps::ReceiveInput = step {
+        bot.send_message(msg.chat.id, "Gotcha! One second...")
+            .await?;
+    }
+    let event = match &msg_common.media_kind {
+        MediaKind::Text(content) => conversation::Inbound::Text(content.text.clone()),
+        MediaKind::Voice(content) => {
+            conversation::Inbound::Audio(download_voice(&bot, content, &msg).await?)
         }
-    })
+        other_media_kinds => {
+            bot.send_message(
+                msg.chat.id,
+                format!(
+                    "I don't know what to do with {} messages. Sorry!",
+                    other_media_kinds.describe()
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let turn = ctx
+        .engine
+        .handle(
+            &msg.chat.id,
+            step,
+            telegram_bot::actor_name(msg.from()),
+            event,
+        )
+        .await;
+    deliver(&bot, msg.chat.id, dialogue, &ctx, turn).await
 }
 
-async fn dispatch<
+/// Handles the Confirm / Cancel buttons under a pending operation's preview.
+async fn press_button<
     T: transcription::interface::TranscriptionClient,
     L: llm::interface::LLMClient<String>,
     S: domain::task::service::TaskDataFlows,
 >(
-    bot: &Bot,
-    msg: &Message,
+    bot: Bot,
+    q: CallbackQuery,
     dialogue: telegram_bot::Dialogue,
     ctx: Arc<Context<T, L, S>>,
-    intent: input::parsing_pipeline_steps::intent::Intent,
-    params: input::parsing_pipeline_steps::params::Extraction,
 ) -> anyhow::Result<()> {
-    Ok(
-        match execution::resolve(intent, params, &ctx.task_data_flows).await {
-            Ok(success_report) => {
-                bot.send_message(msg.chat.id, success_report.formatted_string())
-                    .await?;
-                dialogue
-                    .update(telegram_bot::InteractionSteps::ReceiveInput)
-                    .await;
+    bot.answer_callback_query(q.id.clone()).await?;
+    let Some(msg) = &q.message else {
+        return Ok(());
+    };
+    let Some((button, nonce)) = q
+        .data
+        .as_deref()
+        .and_then(telegram_bot::parse_confirmation_callback_data)
+    else {
+        return Ok(());
+    };
+    // Drop the buttons so the same preview can't be confirmed twice.
+    bot.edit_message_reply_markup(msg.chat.id, msg.id).await?;
+    let step = dialogue.get().await?.unwrap_or_default();
+    let turn = ctx
+        .engine
+        .handle(
+            &msg.chat.id,
+            step,
+            telegram_bot::actor_name(Some(&q.from)),
+            conversation::Inbound::Button { button, nonce },
+        )
+        .await;
+    deliver(&bot, msg.chat.id, dialogue, &ctx, turn).await
+}
+
+/// Shows the engine's reply as Telegram messages and moves the dialogue on. Errors are reported
+/// and the dialogue starts over.
+async fn deliver<
+    T: transcription::interface::TranscriptionClient,
+    L: llm::interface::LLMClient<String>,
+    S: domain::task::service::TaskDataFlows,
+>(
+    bot: &Bot,
+    chat_id: ChatId,
+    dialogue: telegram_bot::Dialogue,
+    ctx: &Context<T, L, S>,
+    turn: anyhow::Result<conversation::Turn>,
+) -> anyhow::Result<()> {
+    let turn = match turn {
+        Ok(turn) => turn,
+        Err(e) => {
+            log::warn!("Conversation failed: {:?}", e);
+            bot.send_message(chat_id, failure_message(&e)).await?;
+            dialogue
+                .update(conversation::InteractionSteps::ReceiveInput)
+                .await?;
+            return Ok(());
+        }
+    };
+    match turn.reply {
+        conversation::Reply::Done { report, reply } => match &report.outcome {
+            execution::Outcome::Tasks(tasks) if !tasks.tasks().is_empty() => {
+                let grouping = list_grouping(&report.params);
+                send_task_list(bot, chat_id, ctx, tasks.tasks(), grouping).await?;
+            }
+            _ => {
+                bot.send_message(chat_id, reply).await?;
             }
-            Err(e) => {
-                match e {
-                    execution::ExecutionErr::InvalidIntentParamPairing {
-                        attempted_intent,
-                        attempted_params,
-                    } => {
-                        // WARN: Getting to this branch indicates a bug in Intent-to-Param
-                        // matching logic
-                        bot.send_message(
-                            msg.chat.id,
-                            format!(
-                                r#"Oops, you've uncovered a bug! Let the dev know. Thanks and sorry!
+        },
+        conversation::Reply::ChooseTask {
+            question,
+            candidates,
+            ..
+        } => {
+            let mut prompt = format!("{}\n", question);
+            for (i, candidate) in candidates.iter().enumerate() {
+                prompt.push_str(&format!("{}. {}\n", i + 1, candidate));
+            }
+            bot.send_message(chat_id, prompt).await?;
+        }
+        conversation::Reply::Confirm {
+            question, nonce, ..
+        } => {
+            bot.send_message(chat_id, question)
+                .reply_markup(telegram_bot::confirmation_keyboard(nonce))
+                .await?;
+        }
+        conversation::Reply::MissingParams { question, .. } => {
+            bot.send_message(chat_id, question).await?;
+        }
+        conversation::Reply::Cancelled { message }
+        | conversation::Reply::NotUnderstood { message }
+        | conversation::Reply::Failed { message } => {
+            bot.send_message(chat_id, message).await?;
+        }
+    }
+    dialogue.update(turn.next).await?;
+    Ok(())
+}
+
+fn failure_message(e: &anyhow::Error) -> String {
+    match e.downcast_ref::<execution::ExecutionErr>() {
+        // WARN: Getting to this branch indicates a bug in Intent-to-Param matching logic
+        Some(execution::ExecutionErr::InvalidIntentParamPairing {
+            attempted_intent,
+            attempted_params,
+        }) => format!(
+            r#"Oops, you've uncovered a bug! Let the dev know. Thanks and sorry!
 Intent-Param-Mismatch
 Intent: {}
 Params: {}
 "#,
-                                attempted_intent, attempted_params
-                            ),
-                        )
-                        .await;
-                    }
-                    // WARN: Everything below indicates some arbitrary service-layer error.
-                    // Use service-layer logs to debug that.
-                    execution::ExecutionErr::TaskCreationError { e }
-                    | execution::ExecutionErr::TaskModificationError { e }
-                    | execution::ExecutionErr::TaskDeletionError { e }
-                    | execution::ExecutionErr::TaskRetrievalError { e } => {
-                        bot.send_message(
-                            msg.chat.id,
-                            format!(
-                                r#"Your operation failed. Here's the error:
+            attempted_intent, attempted_params
+        ),
+        // Anything else is some arbitrary service-layer error; use its logs to debug it.
+        _ => format!(
+            r#"Your operation failed. Here's the error:
 {}"#,
-                                e
-                            ),
-                        )
-                        .await;
-                    }
-                };
-            }
-        },
-    )
+            e
+        ),
+    }
 }
 
-async fn validate_params<
+/// Slash commands, handled ahead of the dialogue branches without any LLM calls.
+async fn handle_command<
     T: transcription::interface::TranscriptionClient,
     L: llm::interface::LLMClient<String>,
     S: domain::task::service::TaskDataFlows,
@@ -233,134 +426,441 @@ async fn validate_params<
     msg: Message,
     dialogue: telegram_bot::Dialogue,
     ctx: Arc<Context<T, L, S>>,
-    input_log: Vec<String>,
-    intent: input::parsing_pipeline_steps::intent::Intent,
-    params: Option<input::parsing_pipeline_steps::params::Extraction>,
+    cmd: telegram_bot::Command,
 ) -> anyhow::Result<()> {
-    let extracted_input = match &msg.kind {
-        MessageKind::Common(msg_common) => match &msg_common.media_kind {
-            MediaKind::Text(content) => Some(content.text.clone()),
-            MediaKind::Voice(content) => transcribe_voice_input(&bot, content, &msg, &ctx).await?,
-            other_media_kinds => {
+    use domain::task::filter::{SortDirection, SortField, TaskFilter, TaskQuery, TaskSort};
+    use input::parsing_pipeline_steps::{intent::Intent, params};
+    let chat_id = msg.chat.id;
+    let by_due_date = vec![TaskSort {
+        field: SortField::DueDate,
+        direction: SortDirection::Ascending,
+    }];
+    let query = match cmd {
+        telegram_bot::Command::Help => {
+            bot.send_message(chat_id, telegram_bot::Command::descriptions().to_string())
+                .await?;
+            return Ok(());
+        }
+        telegram_bot::Command::Cancel => {
+            let reply = match dialogue.get().await?.unwrap_or_default() {
+                conversation::InteractionSteps::ReceiveInput => "There's nothing to cancel.",
+                _ => "Okay, I've dropped that request.",
+            };
+            dialogue
+                .update(conversation::InteractionSteps::ReceiveInput)
+                .await?;
+            bot.send_message(chat_id, reply).await?;
+            return Ok(());
+        }
+        telegram_bot::Command::Undo => {
+            let requester = telegram_bot::requester(msg.from(), String::from("/undo"));
+            let turn = ctx
+                .engine
+                .execute(
+                    &chat_id,
+                    requester,
+                    Intent::Undo,
+                    params::Extraction::Undo {
+                        found: params::Undo::default(),
+                    },
+                )
+                .await;
+            return deliver(&bot, chat_id, dialogue, &ctx, turn).await;
+        }
+        telegram_bot::Command::Done(number) => {
+            let task_id = number
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|number| ctx.listed_tasks.get(chat_id, number));
+            let Some(task_id) = task_id else {
                 bot.send_message(
-                    msg.chat.id,
-                    format!(
-                        "I don't know what to do with {} messages. Sorry!",
-                        other_media_kinds.describe()
-                    ),
+                    chat_id,
+                    "Use /done with a task's number from the last list I sent, e.g. /done 2.",
                 )
                 .await?;
-                None
+                return Ok(());
+            };
+            let requester =
+                telegram_bot::requester(msg.from(), msg.text().unwrap_or_default().to_string());
+            let turn = ctx
+                .engine
+                .execute(
+                    &chat_id,
+                    requester,
+                    Intent::CompleteTask,
+                    params::Extraction::CompleteTask {
+                        found: domain::task::model::PartialTask {
+                            id: Some(task_id),
+                            ..Default::default()
+                        },
+                    },
+                )
+                .await;
+            return deliver(&bot, chat_id, dialogue, &ctx, turn).await;
+        }
+        telegram_bot::Command::RemindMe(name) => {
+            let name = name.trim();
+            let assignee = match (name.is_empty(), msg.from()) {
+                (false, _) => name.to_string(),
+                (true, Some(user)) => user.first_name.clone(),
+                (true, None) => return Ok(()),
+            };
+            ctx.reminders.link(&assignee, chat_id).await?;
+            let mut reply = format!(
+                "Okay, reminders for tasks assigned to {} will come here.",
+                assignee
+            );
+            if ctx.digest_defaults.enabled {
+                let daily_at = ctx.digest_defaults.daily_time();
+                ctx.digests
+                    .set_default(
+                        chat_id,
+                        db::digest::DigestKind::Daily,
+                        daily_at,
+                        ctx.engine.timezone,
+                    )
+                    .await?;
+                reply.push_str(" So will a morning digest of what's due; see /digest.");
             }
+            bot.send_message(chat_id, reply).await?;
+            return Ok(());
+        }
+        telegram_bot::Command::Digest(args) => {
+            return configure_digest(&bot, chat_id, &ctx, &args).await;
+        }
+        telegram_bot::Command::List => TaskQuery {
+            filter: Some(TaskFilter::unfinished()),
+            sort: by_due_date,
+            ..Default::default()
         },
-        _ => None,
-    };
-    Ok(
-        if let Some(params_supplementation_input) = extracted_input {
-            let maybe_additional_params = input::parsing_pipeline_steps::params::extract(
-                &ctx.llm_client,
-                &intent,
-                &params_supplementation_input,
-            )
-            .await;
-            match maybe_additional_params {
-                Ok(additional) => {
-                    use input::parsing_pipeline_steps::params;
-                    let latest_params = match params {
-                    Some(existing) => match ( existing, additional ) {
-                        (params::Extraction::CreateNewTask { found: existing_params }, params::Extraction::CreateNewTask { found: additional_params }) => params::Extraction::CreateNewTask { found: existing_params.merge(additional_params, true) },
-                        (params::Extraction::ModifyExistingTask { found: existing_params }, params::Extraction::ModifyExistingTask { found: additional_params }) => params::Extraction::ModifyExistingTask { found: existing_params.merge(additional_params, true) },
-                        (params::Extraction::DeleteTask { found: existing_params }, params::Extraction::DeleteTask { found: additional_params }) => params::Extraction::DeleteTask { found: existing_params.merge(additional_params, true) },
-                        (params::Extraction::QueryTasks { found: _ }, params::Extraction::QueryTasks { found: additional_params }) => params::Extraction::QueryTasks { found: additional_params},
-                        _ => anyhow::bail!("Error in validation step: Existing params and additional params are not of the same variants")
+        telegram_bot::Command::Mine => {
+            let Some(user) = msg.from() else {
+                return Ok(());
+            };
+            TaskQuery {
+                filter: Some(TaskFilter::And {
+                    filters: vec![
+                        TaskFilter::unfinished(),
+                        TaskFilter::AssigneeEquals {
+                            assignee: user.first_name.clone(),
+                        },
+                    ],
+                }),
+                sort: by_due_date,
+                ..Default::default()
+            }
+        }
+        telegram_bot::Command::Overdue => TaskQuery {
+            filter: Some(TaskFilter::And {
+                filters: vec![
+                    TaskFilter::unfinished(),
+                    TaskFilter::DueBefore {
+                        before: chrono::Utc::now(),
                     },
-                    None => additional,
-                };
+                ],
+            }),
+            sort: by_due_date,
+            ..Default::default()
+        },
+    };
+    let grouping = list_grouping(&params::Extraction::QueryTasks {
+        found: query.clone(),
+    });
+    match ctx.engine.task_data_flows.retrieve_tasks(query).await {
+        Ok(tasks) if tasks.is_empty() => {
+            bot.send_message(chat_id, "No tasks match that.").await?;
+        }
+        Ok(tasks) => send_task_list(&bot, chat_id, &ctx, &tasks, grouping).await?,
+        Err(e) => {
+            bot.send_message(chat_id, format!("I couldn't fetch the tasks: {}", e))
+                .await?;
+        }
+    }
+    Ok(())
+}
 
-                    match latest_params.clone() {
-                        input::parsing_pipeline_steps::params::Extraction::QueryTasks { found } => {
-                            todo!()
-                        }
-                        input::parsing_pipeline_steps::params::Extraction::CreateNewTask {
-                            found,
-                        } => match found.check_complete() {
-                            Ok(_) => {
-                                dispatch(&bot, &msg, dialogue, ctx, intent, latest_params).await;
-                            }
-                            Err(missing_fields) => {
-                                bot.send_message(
-                                    msg.chat.id,
-                                    format!(
-                                        "You're missing the some information before I can process your information: {}",
-                                        missing_fields
-                                    ),
-                                )
-                                .await;
-                                let mut updated_input_log = input_log.clone();
-                                updated_input_log.push(params_supplementation_input);
-                                dialogue.update(telegram_bot::InteractionSteps::ValidateParams {
-                                    input_log: updated_input_log,
-                                    intent,
-                                    params: Some(latest_params),
-                                });
-                            }
-                        },
-                        input::parsing_pipeline_steps::params::Extraction::ModifyExistingTask {
-                            found,
-                        }
-                        | input::parsing_pipeline_steps::params::Extraction::DeleteTask { found } =>
-                        {
-                            match found.check_complete() {
-                                Ok(_) => {
-                                    // TODO: route and resolve execution with full set of params
-                                    todo!()
-                                }
-                                Err(missing_fields) => {
-                                    // TODO: ask for missing fields
-                                    todo!()
-                                }
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    bot.send_message(
-                    msg.chat.id,
+/// `/digest` on its own lists the chat's digests; `/digest <daily|weekly> [HH:MM] [timezone]`
+/// schedules one and `/digest <daily|weekly> off` stops it.
+async fn configure_digest<
+    T: transcription::interface::TranscriptionClient,
+    L: llm::interface::LLMClient<String>,
+    S: domain::task::service::TaskDataFlows,
+>(
+    bot: &Bot,
+    chat_id: ChatId,
+    ctx: &Context<T, L, S>,
+    args: &str,
+) -> anyhow::Result<()> {
+    use db::digest::DigestKind;
+    const USAGE: &str = "Use /digest daily or /digest weekly, followed by a time like 07:30 and a timezone like Europe/London (both optional), or by off.";
+    if !ctx.digest_defaults.enabled {
+        bot.send_message(chat_id, "Digests are turned off for this bot.")
+            .await?;
+        return Ok(());
+    }
+    let mut words = args.split_whitespace();
+    let Some(kind_word) = words.next() else {
+        let schedules = ctx.digests.for_chat(chat_id).await?;
+        let mut reply = String::from(if schedules.is_empty() {
+            "This chat gets no digests."
+        } else {
+            "This chat gets:"
+        });
+        for schedule in schedules {
+            reply.push_str(&format!(
+                "\n- the {} digest {}",
+                schedule.kind.as_str(),
+                digest_timing(schedule.kind, schedule.send_time, schedule.timezone)
+            ));
+        }
+        bot.send_message(chat_id, format!("{}\n{}", reply, USAGE))
+            .await?;
+        return Ok(());
+    };
+    let Some(kind) = DigestKind::from_name(&kind_word.to_lowercase()) else {
+        bot.send_message(chat_id, USAGE).await?;
+        return Ok(());
+    };
+    let time_word = words.next();
+    if time_word.is_some_and(|word| word.eq_ignore_ascii_case("off")) {
+        let reply = if ctx.digests.remove(chat_id, kind).await? {
+            format!("Okay, no more {} digests here.", kind.as_str())
+        } else {
+            format!("This chat wasn't getting the {} digest.", kind.as_str())
+        };
+        bot.send_message(chat_id, reply).await?;
+        return Ok(());
+    }
+    let send_time = match (time_word, kind) {
+        (Some(word), _) => match digest::parse_send_time(word) {
+            Some(send_time) => send_time,
+            None => {
+                bot.send_message(chat_id, USAGE).await?;
+                return Ok(());
+            }
+        },
+        (None, DigestKind::Daily) => ctx.digest_defaults.daily_time(),
+        (None, DigestKind::Weekly) => ctx.digest_defaults.weekly_time(),
+    };
+    let timezone = match words.next() {
+        Some(name) => match name.parse::<chrono_tz::Tz>() {
+            Ok(timezone) => timezone,
+            Err(_) => {
+                bot.send_message(
+                    chat_id,
                     format!(
-                        r#"I ran into an error extracting parameters for your intended operation.
-                        Error: {}
-                        Intent: {}
-                        "#,
-                        e,
-                        intent,
+                        "I don't know the timezone {}. Use a name like Europe/London.",
+                        name
                     ),
-                ).await;
-                }
+                )
+                .await?;
+                return Ok(());
             }
         },
+        None => ctx.engine.timezone,
+    };
+    ctx.digests.set(chat_id, kind, send_time, timezone).await?;
+    let mut reply = format!(
+        "Okay, this chat gets the {} digest {}.",
+        kind.as_str(),
+        digest_timing(kind, send_time, timezone)
+    );
+    if kind == DigestKind::Daily && ctx.reminders.linked_assignees(chat_id).await?.is_empty() {
+        reply.push_str(
+            " It covers the tasks of whoever is linked here with /remindme, and nobody is yet.",
+        );
+    }
+    bot.send_message(chat_id, reply).await?;
+    Ok(())
+}
+
+/// E.g. "on Sundays at 18:00 (Europe/London)".
+fn digest_timing(
+    kind: db::digest::DigestKind,
+    send_time: chrono::NaiveTime,
+    timezone: chrono_tz::Tz,
+) -> String {
+    format!(
+        "{} at {} ({})",
+        match kind {
+            db::digest::DigestKind::Daily => "every day",
+            db::digest::DigestKind::Weekly => "on Sundays",
+        },
+        send_time.format("%H:%M"),
+        timezone.name()
     )
 }
 
-async fn transcribe_voice_input<
+/// Lists ordered by due date read best as a day-by-day agenda; anything else is grouped by who
+/// has to do it.
+fn list_grouping(
+    params: &input::parsing_pipeline_steps::params::Extraction,
+) -> output::telegram::Grouping {
+    let sorted_by_due_date = match params {
+        input::parsing_pipeline_steps::params::Extraction::QueryTasks { found } => found
+            .sort
+            .first()
+            .is_some_and(|sort| matches!(sort.field, domain::task::filter::SortField::DueDate)),
+        _ => false,
+    };
+    if sorted_by_due_date {
+        output::telegram::Grouping::DueDay
+    } else {
+        output::telegram::Grouping::Assignee
+    }
+}
+
+/// Sends the rendered list and remembers its numbering for /done.
+async fn send_task_list<
     T: transcription::interface::TranscriptionClient,
     L: llm::interface::LLMClient<String>,
     S: domain::task::service::TaskDataFlows,
 >(
+    bot: &Bot,
+    chat_id: ChatId,
+    ctx: &Context<T, L, S>,
+    tasks: &[domain::task::model::Task],
+    grouping: output::telegram::Grouping,
+) -> anyhow::Result<()> {
+    let messages = output::telegram::render_task_list(
+        tasks,
+        grouping,
+        chrono::Utc::now().with_timezone(&ctx.engine.timezone),
+    );
+    ctx.listed_tasks.remember(
+        chat_id,
+        messages
+            .iter()
+            .flat_map(|message| message.task_ids.iter().copied())
+            .collect(),
+    );
+    for message in messages {
+        bot.send_message(chat_id, message.html)
+            .parse_mode(ParseMode::Html)
+            .reply_markup(message.keyboard)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Handles the Done / Snooze / Reassign buttons under a listed task. These act on an exact task
+/// id, so they skip resolution and confirmation.
+async fn task_action<
+    T: transcription::interface::TranscriptionClient,
+    L: llm::interface::LLMClient<String>,
+    S: domain::task::service::TaskDataFlows,
+>(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: telegram_bot::Dialogue,
+    ctx: Arc<Context<T, L, S>>,
+) -> anyhow::Result<()> {
+    use input::parsing_pipeline_steps::{intent::Intent, params};
+    bot.answer_callback_query(q.id.clone()).await?;
+    let (Some(msg), Some((action, task_id))) = (
+        q.message.as_ref(),
+        q.data
+            .as_deref()
+            .and_then(telegram_bot::TaskAction::parse_callback_data),
+    ) else {
+        return Ok(());
+    };
+    let chat_id = msg.chat.id;
+    let target = domain::task::model::PartialTask {
+        id: Some(task_id),
+        ..Default::default()
+    };
+    let task = match ctx
+        .engine
+        .task_data_flows
+        .resolve_task(target.clone(), false)
+        .await
+    {
+        Ok(domain::task::resolution::Resolution::Resolved(task)) => task,
+        _ => {
+            bot.send_message(chat_id, "That task no longer exists.")
+                .await?;
+            return Ok(());
+        }
+    };
+    let requester = telegram_bot::requester(
+        Some(&q.from),
+        format!("[{:?} button] {}", action, task.description),
+    );
+    match action {
+        telegram_bot::TaskAction::Done => {
+            let turn = ctx
+                .engine
+                .execute(
+                    &chat_id,
+                    requester,
+                    Intent::CompleteTask,
+                    params::Extraction::CompleteTask { found: target },
+                )
+                .await;
+            deliver(&bot, chat_id, dialogue, &ctx, turn).await
+        }
+        telegram_bot::TaskAction::Snooze => {
+            let changes = params::TaskChanges {
+                due_date: Some(
+                    task.due_date + chrono::Duration::days(telegram_bot::TaskAction::SNOOZE_DAYS),
+                ),
+                ..Default::default()
+            };
+            let turn = ctx
+                .engine
+                .execute(
+                    &chat_id,
+                    requester,
+                    Intent::ModifyExistingTask,
+                    params::Extraction::ModifyExistingTask {
+                        found: params::ModifyExistingTask { target, changes },
+                    },
+                )
+                .await;
+            deliver(&bot, chat_id, dialogue, &ctx, turn).await
+        }
+        telegram_bot::TaskAction::Reassign => {
+            bot.send_message(
+                chat_id,
+                format!(
+                    "Who should \"{}\" go to instead? (Send /cancel to keep it with {}.)",
+                    task.description, task.assignee
+                ),
+            )
+            .await?;
+            dialogue
+                .update(conversation::InteractionSteps::ValidateParams {
+                    input_log: vec![format!(
+                        "Reassign \"{}\" to someone else.",
+                        task.description
+                    )],
+                    intent: Intent::ModifyExistingTask,
+                    params: Some(params::Extraction::ModifyExistingTask {
+                        found: params::ModifyExistingTask {
+                            target,
+                            changes: Default::default(),
+                        },
+                    }),
+                })
+                .await?;
+            Ok(())
+        }
+    }
+}
+
+async fn download_voice(
     bot: &Bot,
     content: &teloxide::types::MediaVoice,
     msg: &Message,
-    ctx: &Context<T, L, S>,
-) -> Result<Option<String>, anyhow::Error> {
+) -> anyhow::Result<Vec<u8>> {
     let TelegramFile { path, .. } = bot.get_file(&content.voice.file.id).await?;
     let tmp_file_name = format!("/tmp/transcribe/{}.ogg", msg.id);
     let mut dst = TokioFile::create(&tmp_file_name).await?;
     bot.download_file(&path, &mut dst).await?;
     let mut download_as_buffer = Vec::<u8>::new();
     dst.read_to_end(&mut download_as_buffer).await?;
-    let transcript = ctx
-        .transcription_client
-        .transcribe(download_as_buffer)
-        .await?;
     fs::remove_file(&tmp_file_name).await?;
-    Ok(Some(transcript))
+    Ok(download_as_buffer)
 }
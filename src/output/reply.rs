@@ -0,0 +1,143 @@
+use crate::domain::task::model::Task;
+use crate::execution::{Outcome, SuccessReport};
+use crate::input::parsing_pipeline_steps::intent::Intent;
+use crate::llm::interface::LLMClient;
+use chrono_tz::Tz;
+use serde::Deserialize;
+use serde_json::json;
+
+/// How the reply to an executed operation is written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Renderer {
+    /// Ask the LLM for a conversational reply, falling back to the template if that fails.
+    #[default]
+    Llm,
+    /// Always use the deterministic template.
+    Template,
+}
+static RENDER_SYSTEM_PROMPT: &str = r#"You write the chat reply after a task-management operation has run.
+You will be given the user's intent, the parameters it was run with and the outcome, as JSON.
+Reply in one to three short, friendly sentences that confirm what happened. Mention task descriptions, assignees and due dates as plain words (e.g. "Friday 6pm"), never ids or raw timestamps, and give times in the given timezone.
+For a list of tasks, use one line per task. Respond with a JSON object of the form {"reply": "<text>"}."#;
+
+#[derive(Deserialize)]
+struct RenderedReply {
+    reply: String,
+}
+
+/// Writes the user-facing reply for a successful operation.
+pub async fn render(
+    renderer: Renderer,
+    llm_client: &impl LLMClient<String>,
+    report: &SuccessReport<Outcome>,
+    timezone: Tz,
+) -> String {
+    match renderer {
+        Renderer::Template => template(report, timezone),
+        Renderer::Llm => match render_with_llm(llm_client, report, timezone).await {
+            Ok(reply) => reply,
+            Err(e) => {
+                log::warn!("LLM reply rendering failed, using the template: {}", e);
+                template(report, timezone)
+            }
+        },
+    }
+}
+
+async fn render_with_llm(
+    llm_client: &impl LLMClient<String>,
+    report: &SuccessReport<Outcome>,
+    timezone: Tz,
+) -> anyhow::Result<String> {
+    let outcome = match &report.outcome {
+        Outcome::Task(task) => serde_json::to_value(task)?,
+        Outcome::Tasks(tasks) => serde_json::to_value(tasks.tasks())?,
+        Outcome::History(events) => serde_json::to_value(&events.0)?,
+    };
+    let prompt = json!({
+        "intent": report.intent,
+        "params": report.params,
+        "outcome": outcome,
+        "timezone": timezone.name(),
+    });
+    let llm_response = llm_client
+        .prompt_system_customized(&prompt.to_string(), RENDER_SYSTEM_PROMPT)
+        .await?;
+    let rendered: RenderedReply = serde_json::from_str(&llm_response)?;
+    if rendered.reply.trim().is_empty() {
+        anyhow::bail!("LLM rendered an empty reply");
+    }
+    Ok(rendered.reply)
+}
+
+/// Deterministic reply covering every intent; used directly or when the LLM is unavailable.
+pub fn template(report: &SuccessReport<Outcome>, timezone: Tz) -> String {
+    match (&report.intent, &report.outcome) {
+        (_, Outcome::Tasks(tasks)) => match tasks.tasks() {
+            [] => String::from("No tasks match that."),
+            tasks => {
+                let mut reply = format!(
+                    "Found {} task{}:",
+                    tasks.len(),
+                    if tasks.len() == 1 { "" } else { "s" }
+                );
+                for task in tasks {
+                    reply.push_str(&format!("\n- {}", summary(task, timezone)));
+                }
+                reply
+            }
+        },
+        (_, Outcome::History(events)) => match events.0.as_slice() {
+            [] => String::from("There are no recorded changes for that task."),
+            events => {
+                let mut reply = String::from("Here's what happened to it:");
+                for event in events {
+                    reply.push_str(&format!(
+                        "\n- {}: {} ({}) said \"{}\"",
+                        event
+                            .occurred_at
+                            .with_timezone(&timezone)
+                            .format("%a %d %b %H:%M"),
+                        event.actor,
+                        event.intent,
+                        event.source_text
+                    ));
+                }
+                reply
+            }
+        },
+        (Intent::CreateNewTask, Outcome::Task(task)) => {
+            format!("Added: {}.", summary(task, timezone))
+        }
+        (Intent::ModifyExistingTask, Outcome::Task(task)) => {
+            format!("Updated. It's now: {}.", summary(task, timezone))
+        }
+        (Intent::DeleteTask, Outcome::Task(task)) => {
+            format!("Deleted \"{}\".", task.description)
+        }
+        (Intent::CompleteTask, Outcome::Task(task)) => {
+            format!("Nice! Marked \"{}\" as done.", task.description)
+        }
+        (Intent::Undo, Outcome::Task(task)) if task.deleted_at.is_some() => {
+            format!("Undone: removed \"{}\" again.", task.description)
+        }
+        (Intent::Undo, Outcome::Task(task)) => {
+            format!("Undone. Restored: {}.", summary(task, timezone))
+        }
+        (_, Outcome::Task(task)) => format!("Done: {}.", summary(task, timezone)),
+    }
+}
+
+/// One-line description of a task, e.g. `"Take the bins out" for Alex, due Fri 07 Jun 18:00`.
+fn summary(task: &Task, timezone: Tz) -> String {
+    format!(
+        "\"{}\" for {}, due {} ({})",
+        task.description,
+        task.assignee,
+        task.due_date
+            .with_timezone(&timezone)
+            .format("%a %d %b %H:%M"),
+        task.status
+    )
+}
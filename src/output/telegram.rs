@@ -0,0 +1,228 @@
+use crate::domain::task::model::{Task, TaskStatus};
+use crate::telegram_bot::TaskAction;
+use chrono::DateTime;
+use chrono_tz::Tz;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use uuid::Uuid;
+
+/// Telegram rejects messages longer than this many characters.
+pub const MESSAGE_LIMIT: usize = 4096;
+/// Each task gets a row of `TaskAction` buttons and a message can carry at most 100 buttons.
+const MAX_TASKS_PER_MESSAGE: usize = 30;
+/// Longest escaped description and assignee shown, so a single task always fits in a message
+/// together with its group heading.
+const DESCRIPTION_LIMIT: usize = 3000;
+const ASSIGNEE_LIMIT: usize = 200;
+
+/// How a task list is broken into sections.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Grouping {
+    Assignee,
+    DueDay,
+}
+
+/// One Telegram message of a rendered task list: HTML text (send with `ParseMode::Html`) and the
+/// action buttons for the tasks it contains.
+pub struct RenderedMessage {
+    pub html: String,
+    pub keyboard: InlineKeyboardMarkup,
+    /// The tasks in this message, in the order they are numbered.
+    pub task_ids: Vec<Uuid>,
+}
+
+/// Renders tasks as HTML sections grouped by `grouping`, numbered continuously across messages,
+/// with overdue open tasks flagged. Splits into as many messages as Telegram's limits require.
+/// Days and times are shown in `now`'s timezone.
+pub fn render_task_list(
+    tasks: &[Task],
+    grouping: Grouping,
+    now: DateTime<Tz>,
+) -> Vec<RenderedMessage> {
+    let timezone = now.timezone();
+    let mut sorted: Vec<&Task> = tasks.iter().collect();
+    // Stable, so the query's own order is kept within each group.
+    sorted.sort_by(|a, b| group_key(a, grouping, timezone).cmp(&group_key(b, grouping, timezone)));
+
+    let mut messages = vec![];
+    let mut html = String::new();
+    let mut rows: Vec<Vec<InlineKeyboardButton>> = vec![];
+    let mut task_ids = vec![];
+    let mut current_group: Option<String> = None;
+    for (i, task) in sorted.into_iter().enumerate() {
+        let number = i + 1;
+        let group = group_key(task, grouping, timezone);
+        let mut chunk = String::new();
+        let starts_group = current_group.as_ref() != Some(&group);
+        if starts_group {
+            chunk.push_str(&format!(
+                "\n<b>{}</b>\n",
+                escape_truncated(&group_heading(task, grouping, timezone), ASSIGNEE_LIMIT)
+            ));
+        }
+        chunk.push_str(&task_line(number, task, grouping, now));
+
+        if !rows.is_empty()
+            && (html.chars().count() + chunk.chars().count() > MESSAGE_LIMIT
+                || rows.len() >= MAX_TASKS_PER_MESSAGE)
+        {
+            messages.push(RenderedMessage {
+                html: html.trim().to_string(),
+                keyboard: InlineKeyboardMarkup::new(std::mem::take(&mut rows)),
+                task_ids: std::mem::take(&mut task_ids),
+            });
+            html.clear();
+            // Repeat the heading so a continued group still makes sense on its own.
+            if !starts_group {
+                chunk = format!(
+                    "\n<b>{} (cont.)</b>\n{}",
+                    escape_truncated(&group_heading(task, grouping, timezone), ASSIGNEE_LIMIT),
+                    task_line(number, task, grouping, now)
+                );
+            }
+        }
+        html.push_str(&chunk);
+        rows.push(task_buttons(number, task));
+        task_ids.push(task.id);
+        current_group = Some(group);
+    }
+    if !rows.is_empty() {
+        messages.push(RenderedMessage {
+            html: html.trim().to_string(),
+            keyboard: InlineKeyboardMarkup::new(rows),
+            task_ids,
+        });
+    }
+    messages
+}
+
+fn group_key(task: &Task, grouping: Grouping, timezone: Tz) -> String {
+    match grouping {
+        Grouping::Assignee => task.assignee.to_lowercase(),
+        Grouping::DueDay => local_due(task, timezone).format("%Y-%m-%d").to_string(),
+    }
+}
+
+fn group_heading(task: &Task, grouping: Grouping, timezone: Tz) -> String {
+    match grouping {
+        Grouping::Assignee => task.assignee.clone(),
+        Grouping::DueDay => local_due(task, timezone).format("%A %d %B").to_string(),
+    }
+}
+
+fn task_line(number: usize, task: &Task, grouping: Grouping, now: DateTime<Tz>) -> String {
+    let due = local_due(task, now.timezone());
+    let detail = match grouping {
+        Grouping::Assignee => escape(&due.format("due %a %d %b %H:%M").to_string()),
+        Grouping::DueDay => format!(
+            "{}, {}",
+            escape_truncated(&task.assignee, ASSIGNEE_LIMIT),
+            due.format("%H:%M")
+        ),
+    };
+    let description = escape_truncated(&task.description, DESCRIPTION_LIMIT);
+    let description = match task.status {
+        TaskStatus::Done | TaskStatus::Cancelled => format!("<s>{}</s>", description),
+        TaskStatus::Open | TaskStatus::InProgress => description,
+    };
+    let overdue = is_overdue(task, now);
+    format!(
+        "{}{}. {} — {}{}\n",
+        if overdue { "⚠️ " } else { "" },
+        number,
+        description,
+        detail,
+        if overdue { " <b>(overdue)</b>" } else { "" }
+    )
+}
+
+fn local_due(task: &Task, timezone: Tz) -> DateTime<Tz> {
+    task.due_date.with_timezone(&timezone)
+}
+
+fn is_overdue(task: &Task, now: DateTime<Tz>) -> bool {
+    matches!(task.status, TaskStatus::Open | TaskStatus::InProgress) && task.due_date < now
+}
+
+fn task_buttons(number: usize, task: &Task) -> Vec<InlineKeyboardButton> {
+    [
+        (TaskAction::Done, "✅ Done"),
+        (TaskAction::Snooze, "⏰ Snooze"),
+        (TaskAction::Reassign, "👤 Reassign"),
+    ]
+    .into_iter()
+    .map(|(action, label)| {
+        InlineKeyboardButton::callback(
+            format!("{} #{}", label, number),
+            action.callback_data(task.id),
+        )
+    })
+    .collect()
+}
+
+/// Escapes the characters Telegram's HTML parse mode treats as markup.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Like `escape`, but cut off with "…" at a character boundary if the escaped text would be
+/// longer than `limit` characters. Never cuts inside an entity.
+fn escape_truncated(text: &str, limit: usize) -> String {
+    let escaped = escape(text);
+    if escaped.chars().count() <= limit {
+        return escaped;
+    }
+    let mut truncated = String::new();
+    let mut length = 0;
+    for c in text.chars() {
+        let piece = escape(c.encode_utf8(&mut [0; 4]));
+        length += piece.chars().count();
+        if length > limit - 1 {
+            break;
+        }
+        truncated.push_str(&piece);
+    }
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn long_descriptions_are_truncated_to_fit_one_message() {
+        let now = Utc
+            .with_ymd_and_hms(2026, 10, 18, 9, 0, 0)
+            .unwrap()
+            .with_timezone(&Tz::UTC);
+        let task = Task {
+            id: Uuid::new_v4(),
+            description: "Tom & Jerry ".repeat(1000),
+            create_date: now.with_timezone(&Utc),
+            due_date: now.with_timezone(&Utc),
+            assignee: "Sam".repeat(500),
+            status: TaskStatus::Done,
+            completed_at: None,
+            deleted_at: None,
+        };
+
+        for grouping in [Grouping::Assignee, Grouping::DueDay] {
+            let messages = render_task_list(std::slice::from_ref(&task), grouping, now);
+
+            assert_eq!(messages.len(), 1);
+            let html = &messages[0].html;
+            assert!(html.chars().count() <= MESSAGE_LIMIT);
+            assert!(html.contains("Tom &amp; Jerry"));
+            assert!(html.contains("…</s>"));
+        }
+    }
+
+    #[test]
+    fn short_text_is_only_escaped() {
+        assert_eq!(escape_truncated("a < b", 10), "a &lt; b");
+        assert_eq!(escape_truncated("a & b", 6), "a …");
+    }
+}
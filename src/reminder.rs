@@ -0,0 +1,134 @@
+use crate::db;
+use crate::domain::task::filter::{TaskFilter, TaskQuery, MAX_QUERY_LIMIT};
+use crate::domain::task::model::Task;
+use crate::domain::task::service::TaskDataFlows;
+use crate::output::telegram::{self, Grouping};
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use std::sync::Arc;
+use teloxide::prelude::*;
+use teloxide::types::ParseMode;
+use tokio::time::MissedTickBehavior;
+
+/// Messages assignees in their linked chat as their open tasks come due. It polls rather than
+/// setting a timer per task, so tasks created, snoozed or finished in the meantime need no
+/// bookkeeping; what has been sent is tracked in `db::reminder::Store`.
+pub struct Scheduler<S> {
+    pub task_data_flows: S,
+    pub store: Arc<db::reminder::Store>,
+    /// How long before the due date each reminder goes out.
+    pub offsets: Vec<Duration>,
+    pub poll_interval: std::time::Duration,
+    /// Due dates in reminders are shown in this timezone.
+    pub timezone: Tz,
+}
+
+impl<S: TaskDataFlows> Scheduler<S> {
+    /// Runs until the process exits. A failed round is logged and retried on the next tick.
+    pub async fn run(self, bot: Bot) {
+        let mut ticks = tokio::time::interval(self.poll_interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticks.tick().await;
+            if let Err(e) = self.send_due(&bot).await {
+                log::error!("Sending reminders failed: {:?}", e);
+            }
+        }
+    }
+
+    async fn send_due(&self, bot: &Bot) -> anyhow::Result<()> {
+        let Some(furthest) = self.offsets.iter().max().copied() else {
+            return Ok(());
+        };
+        let now = Utc::now();
+        let tasks = self
+            .task_data_flows
+            .retrieve_tasks(TaskQuery {
+                filter: Some(TaskFilter::And {
+                    filters: vec![
+                        TaskFilter::unfinished(),
+                        TaskFilter::DueBetween {
+                            start: now,
+                            end: now + furthest,
+                        },
+                    ],
+                }),
+                limit: Some(MAX_QUERY_LIMIT),
+                ..Default::default()
+            })
+            .await?;
+        for task in tasks {
+            // Only the nearest reminder that has come due goes out, so a task created (or a bot
+            // started) late doesn't get a burst of stale ones.
+            let Some(offset) = self
+                .offsets
+                .iter()
+                .copied()
+                .filter(|offset| task.due_date - *offset <= now)
+                .min()
+            else {
+                continue;
+            };
+            if let Err(e) = self.remind(bot, &task, offset, now).await {
+                log::warn!("Could not remind about task {}: {:?}", task.id, e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn remind(
+        &self,
+        bot: &Bot,
+        task: &Task,
+        offset: Duration,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        // Unlinked assignees aren't claimed, so linking later still gets them the reminder.
+        let Some(chat_id) = self.store.linked_chat(&task.assignee).await? else {
+            return Ok(());
+        };
+        if !self
+            .store
+            .claim(task.id, offset.num_minutes(), task.due_date)
+            .await?
+        {
+            return Ok(());
+        }
+        let rendered = telegram::render_task_list(
+            std::slice::from_ref(task),
+            Grouping::Assignee,
+            now.with_timezone(&self.timezone),
+        );
+        for message in rendered {
+            bot.send_message(
+                chat_id,
+                format!(
+                    "⏰ <b>Reminder:</b> due {}\n{}",
+                    due_in(task.due_date - now),
+                    message.html
+                ),
+            )
+            .parse_mode(ParseMode::Html)
+            .reply_markup(message.keyboard)
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+/// "in 45 minutes", "in 3 hours", "in 2 days".
+fn due_in(remaining: Duration) -> String {
+    let (amount, unit) = if remaining < Duration::hours(1) {
+        (remaining.num_minutes().max(1), "minute")
+    } else if remaining < Duration::days(2) {
+        (remaining.num_hours(), "hour")
+    } else {
+        (remaining.num_days(), "day")
+    };
+    format!(
+        "in {} {}{}",
+        amount,
+        unit,
+        if amount == 1 { "" } else { "s" }
+    )
+}
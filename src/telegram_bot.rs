@@ -1,24 +1,156 @@
 use crate::{
+    config,
+    conversation::{self, InteractionSteps},
+    db,
     domain::task::service::TaskDataFlows,
-    input::parsing_pipeline_steps::{intent::Intent, params::Extraction},
-    llm, transcription,
+    execution, llm, transcription,
 };
 use core::fmt;
-
-pub const TELEGRAM_BOT_API_KEY: &str = "7011341978:AAE0gWDYiKkRHJYEgPXibP48itZiMuzcKAE";
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use teloxide::utils::command::BotCommands;
+use uuid::Uuid;
 
 use teloxide::dispatching::dialogue;
-pub type Dialogue = dialogue::Dialogue<InteractionSteps, dialogue::InMemStorage<InteractionSteps>>;
+use teloxide::types::{ChatId, InlineKeyboardButton, InlineKeyboardMarkup, User};
+pub type Dialogue = dialogue::Dialogue<InteractionSteps, db::dialogue::Storage>;
+
+const CONFIRM_CALLBACK: &str = "confirm";
+const CANCEL_CALLBACK: &str = "cancel";
+
+/// Confirm / Cancel under a pending operation's preview. The buttons carry the operation's
+/// nonce, so pressing them on an older preview can't answer whatever is pending now.
+pub fn confirmation_keyboard(nonce: Uuid) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new([[
+        InlineKeyboardButton::callback(
+            "Confirm",
+            confirmation_callback_data(conversation::Button::Confirm, nonce),
+        ),
+        InlineKeyboardButton::callback(
+            "Cancel",
+            confirmation_callback_data(conversation::Button::Cancel, nonce),
+        ),
+    ]])
+}
+
+fn confirmation_prefix(button: conversation::Button) -> &'static str {
+    match button {
+        conversation::Button::Confirm => CONFIRM_CALLBACK,
+        conversation::Button::Cancel => CANCEL_CALLBACK,
+    }
+}
+
+/// `<answer>:<nonce>`, like `TaskAction::callback_data`.
+fn confirmation_callback_data(button: conversation::Button, nonce: Uuid) -> String {
+    format!("{}:{}", confirmation_prefix(button), nonce)
+}
 
-#[derive(Clone, Default)]
-pub enum InteractionSteps {
-    #[default]
-    ReceiveInput,
-    ValidateParams {
-        input_log: Vec<String>,
-        intent: Intent,
-        params: Option<Extraction>,
-    },
+pub fn parse_confirmation_callback_data(data: &str) -> Option<(conversation::Button, Uuid)> {
+    let (prefix, nonce) = data.split_once(':')?;
+    let button = [conversation::Button::Confirm, conversation::Button::Cancel]
+        .into_iter()
+        .find(|button| confirmation_prefix(*button) == prefix)?;
+    Some((button, nonce.parse().ok()?))
+}
+
+#[derive(BotCommands, Clone)]
+#[command(
+    rename_rule = "lowercase",
+    description = "These commands work instantly. Anything else is read as a request in plain words."
+)]
+pub enum Command {
+    #[command(description = "show this message.")]
+    Help,
+    #[command(description = "list all open tasks.")]
+    List,
+    #[command(description = "list the tasks assigned to you.")]
+    Mine,
+    #[command(description = "list tasks that are past due.")]
+    Overdue,
+    #[command(description = "mark task <n> from the last list as done.")]
+    Done(String),
+    #[command(description = "drop the request in progress.")]
+    Cancel,
+    #[command(description = "undo the last change.")]
+    Undo,
+    #[command(description = "send reminders for <name>'s tasks to this chat (default: you).")]
+    RemindMe(String),
+    #[command(
+        description = "show or set this chat's digests: /digest daily|weekly [HH:MM] [timezone], or /digest daily|weekly off."
+    )]
+    Digest(String),
+}
+
+/// The task ids of the last list sent to each chat, in display order, so /done can refer to
+/// tasks by number.
+#[derive(Default)]
+pub struct ListedTasks(Mutex<HashMap<ChatId, Vec<Uuid>>>);
+impl ListedTasks {
+    pub fn remember(&self, chat_id: ChatId, task_ids: Vec<Uuid>) {
+        self.0
+            .lock()
+            .expect("listed tasks lock poisoned")
+            .insert(chat_id, task_ids);
+    }
+
+    /// The task shown as `number` (counting from 1) in the last list.
+    pub fn get(&self, chat_id: ChatId, number: usize) -> Option<Uuid> {
+        let listed = self.0.lock().expect("listed tasks lock poisoned");
+        listed.get(&chat_id)?.get(number.checked_sub(1)?).copied()
+    }
+}
+
+/// Buttons shown under each task in a rendered task list.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TaskAction {
+    Done,
+    /// Push the due date back by `SNOOZE_DAYS`.
+    Snooze,
+    /// Ask who the task should go to instead.
+    Reassign,
+}
+impl TaskAction {
+    pub const SNOOZE_DAYS: i64 = 1;
+
+    fn prefix(&self) -> &'static str {
+        match self {
+            TaskAction::Done => "done",
+            TaskAction::Snooze => "snooze",
+            TaskAction::Reassign => "reassign",
+        }
+    }
+
+    /// `<action>:<task id>`, well within Telegram's 64-byte callback data limit.
+    pub fn callback_data(&self, task_id: Uuid) -> String {
+        format!("{}:{}", self.prefix(), task_id)
+    }
+
+    pub fn parse_callback_data(data: &str) -> Option<(Self, Uuid)> {
+        let (prefix, id) = data.split_once(':')?;
+        let action = [TaskAction::Done, TaskAction::Snooze, TaskAction::Reassign]
+            .into_iter()
+            .find(|action| action.prefix() == prefix)?;
+        Some((action, id.parse().ok()?))
+    }
+}
+
+/// How a Telegram user is named in the task history: their @username if they have one, with the
+/// numeric id to keep entries unambiguous.
+pub fn actor_name(user: Option<&User>) -> String {
+    match user {
+        Some(user) => match &user.username {
+            Some(username) => format!("@{} ({})", username, user.id),
+            None => format!("{} ({})", user.full_name(), user.id),
+        },
+        None => String::from("unknown"),
+    }
+}
+
+pub fn requester(user: Option<&User>, source_text: String) -> execution::Requester {
+    execution::Requester {
+        user: actor_name(user),
+        source_text,
+    }
 }
 
 pub struct Context<
@@ -26,9 +158,13 @@ pub struct Context<
     L: llm::interface::LLMClient<String>,
     S: TaskDataFlows,
 > {
-    pub transcription_client: T,
-    pub llm_client: L,
-    pub task_data_flows: S,
+    pub engine: conversation::Engine<T, L, S, ChatId>,
+    pub listed_tasks: ListedTasks,
+    /// Where `/remindme` links assignees to chats for the reminder scheduler.
+    pub reminders: Arc<db::reminder::Store>,
+    pub digests: Arc<db::digest::Store>,
+    /// Digest times for chats that don't give their own.
+    pub digest_defaults: config::DigestConfig,
     pub chat_log: Vec<String>,
 }
 
@@ -1 +1,32 @@
 pub mod openai;
+#[cfg(test)]
+pub mod scripted;
+
+use super::interface::TranscriptionClient;
+use crate::config::{TranscriptionBackend, TranscriptionConfig};
+use async_trait::async_trait;
+
+/// Backend chosen at startup, like `llm::backend::AnyClient`, so the rest of the bot can stay
+/// generic over a single `TranscriptionClient` type.
+pub enum AnyTranscriptionClient {
+    OpenAI(openai::WhisperClient),
+}
+
+impl AnyTranscriptionClient {
+    pub fn from_config(config: &TranscriptionConfig) -> Self {
+        match config.backend {
+            TranscriptionBackend::OpenAI => {
+                Self::OpenAI(openai::WhisperClient::new(config.model.clone()))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl TranscriptionClient for AnyTranscriptionClient {
+    async fn transcribe(&self, audio_file_buf: Vec<u8>) -> anyhow::Result<String> {
+        match self {
+            AnyTranscriptionClient::OpenAI(client) => client.transcribe(audio_file_buf).await,
+        }
+    }
+}
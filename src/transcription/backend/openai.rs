@@ -6,31 +6,36 @@ use async_trait::async_trait;
 
 pub struct WhisperClient {
     inner_client: InnerClient<OpenAIConfig>,
+    model: String,
 }
 
 #[async_trait]
 impl interface::TranscriptionClient for WhisperClient {
     async fn transcribe(&self, audio_file_buf: Vec<u8>) -> anyhow::Result<String> {
-        Self::transcribe_inner(&self.inner_client, audio_file_buf).await
+        Self::transcribe_inner(&self.inner_client, &self.model, audio_file_buf).await
     }
 }
 
 impl WhisperClient {
     /// Requires setting of OPENAI_API_KEY env var
-    pub fn new() -> Self {
+    pub fn new(model: String) -> Self {
         let inner_client = InnerClient::new();
-        Self { inner_client }
+        Self {
+            inner_client,
+            model,
+        }
     }
 
     async fn transcribe_inner(
         client: &InnerClient<OpenAIConfig>,
+        model: &str,
         audio_file_buf: Vec<u8>,
     ) -> anyhow::Result<String> {
         let audio_input = AudioInput::from_vec_u8("tmp.mp3".to_string(), audio_file_buf);
 
         let request = CreateTranscriptionRequestArgs::default()
             .file(audio_input)
-            .model("whisper-1")
+            .model(model)
             .response_format(AudioResponseFormat::Text)
             .build()?;
 
@@ -0,0 +1,74 @@
+use crate::transcription::interface;
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Deterministic stand-in for a speech-to-text service: replays canned transcripts and records
+/// every audio buffer it receives.
+pub struct Client {
+    script: Mutex<Script>,
+    recorded: Mutex<Vec<Vec<u8>>>,
+}
+
+enum Script {
+    /// Transcripts are handed out in order, one per call, regardless of the audio.
+    Sequence(VecDeque<String>),
+    /// The entry whose audio bytes equal the input answers.
+    Keyed(Vec<(Vec<u8>, String)>),
+}
+
+#[async_trait]
+impl interface::TranscriptionClient for Client {
+    async fn transcribe(&self, audio_file_buf: Vec<u8>) -> anyhow::Result<String> {
+        let transcript = match &mut *self.script.lock().expect("script lock poisoned") {
+            Script::Sequence(transcripts) => transcripts.pop_front(),
+            Script::Keyed(transcripts) => transcripts
+                .iter()
+                .find(|(audio, _)| *audio == audio_file_buf)
+                .map(|(_, transcript)| transcript.clone()),
+        };
+        let received_len = audio_file_buf.len();
+        self.recorded
+            .lock()
+            .expect("recording lock poisoned")
+            .push(audio_file_buf);
+        transcript.ok_or_else(|| {
+            anyhow::anyhow!(
+                "Scripted transcription has no transcript for {} bytes of audio",
+                received_len
+            )
+        })
+    }
+}
+
+impl Client {
+    pub fn sequence<S: Into<String>>(transcripts: impl IntoIterator<Item = S>) -> Self {
+        Self::with_script(Script::Sequence(
+            transcripts.into_iter().map(Into::into).collect(),
+        ))
+    }
+
+    pub fn keyed<S: Into<String>>(transcripts: impl IntoIterator<Item = (Vec<u8>, S)>) -> Self {
+        Self::with_script(Script::Keyed(
+            transcripts
+                .into_iter()
+                .map(|(audio, transcript)| (audio, transcript.into()))
+                .collect(),
+        ))
+    }
+
+    /// Every audio buffer received so far, oldest first.
+    pub fn recorded_audio(&self) -> Vec<Vec<u8>> {
+        self.recorded
+            .lock()
+            .expect("recording lock poisoned")
+            .clone()
+    }
+
+    fn with_script(script: Script) -> Self {
+        Self {
+            script: Mutex::new(script),
+            recorded: Mutex::new(vec![]),
+        }
+    }
+}